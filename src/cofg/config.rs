@@ -15,7 +15,7 @@
 use log::{debug, error, warn};
 use nest_struct::nest_struct;
 use std::{
-	collections::HashSet,
+	collections::{HashMap, HashSet},
 	sync::{OnceLock, RwLock},
 };
 
@@ -32,8 +32,89 @@ pub(crate) struct XdgPaths {
 	pub(crate) emojis: std::path::PathBuf,
 }
 
+/// How highlighted code carries its colors (`markdown.highlight.mode`).
+#[cfg_attr(not(feature = "highlight"), allow(dead_code))]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HighlightMode {
+	/// `hl-*` classes; colors come from the served stylesheet
+	Class,
+	/// `style="..."` on every span; self-contained but larger
+	Inline,
+}
+
+/// What happens to raw HTML written in markdown (`markdown.sanitize.policy`).
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HtmlPolicy {
+	/// Pass through unchanged; only for fully trusted content
+	Allow,
+	/// Allow-list of tags/attributes; scripts, event handlers and unsafe URLs are removed
+	Safe,
+	/// Render raw HTML as visible text
+	Escape,
+}
+
+/// Whether the first CSV/TSV row is a header (`table.header`).
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TableHeader {
+	/// Header when every first-row cell is non-empty, non-numeric and distinct
+	Auto,
+	Always,
+	Never,
+}
+
+/// Order of TOC / nav entries (`toc.sort`).
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TocSort {
+	/// File name
+	#[default]
+	Name,
+	/// Front matter `title` / first H1, else file name
+	Title,
+	/// Front matter `order` (or `weight`), ascending; entries without one last
+	Order,
+	/// Modification time, newest first (directories by their newest file)
+	Mtime,
+}
+
+/// How a directory page is presented (`directory.view`, `?view=`).
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DirectoryView {
+	/// `gallery` / `media` when enough of the directory's files are images / audio-video, else `toc`
+	Auto,
+	/// Markdown TOC of `toc.ext` files (recursive)
+	Toc,
+	/// Index of every entry with size, modification time and type
+	List,
+	/// Thumbnail grid
+	Gallery,
+	/// Inline audio/video players
+	Media,
+}
+
+/// How a `redirects` rule's `from` is compared with the request path.
+#[derive(
+	PartialEq, Eq, Hash, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RedirectMatch {
+	/// The whole path
+	#[default]
+	Exact,
+	/// Whole leading segments; the rest of the path is appended to `to`
+	Prefix,
+	/// wax glob; `$1`.. are its wildcards
+	Glob,
+	/// Regular expression over the whole path; `$1` / `${name}` are its groups
+	Regex,
+}
+
 #[nest_struct]
-#[derive(PartialEq, Clone, Debug, serde::Deserialize)]
+#[derive(PartialEq, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) struct Cofg {
 	pub(crate) addrs: nest! {
 	  /// Server IP address (e.g., 127.0.0.1)
@@ -106,11 +187,207 @@ pub(crate) struct Cofg {
 	  /// Enable hot-reloading of templates
 	  pub(crate) hot_reload: bool
 	},
+	/// Markdown rendering options
+	#[serde(default)]
+	pub(crate) markdown: nest! {
+	  /// Which markdown_ppp constructs are recognised; disabled ones are read as plain text
+	  pub(crate) parser: nest! {
+		/// Accept `#Heading` without a space after the hashes
+		pub(crate) allow_no_space_in_headings: bool,
+		pub(crate) blockquote: bool,
+		pub(crate) github_alert: bool,
+		/// `# Heading`
+		pub(crate) heading_atx: bool,
+		/// `Heading` underlined with `===` / `---`
+		pub(crate) heading_setext: bool,
+		pub(crate) thematic_break: bool,
+		pub(crate) list: bool,
+		pub(crate) code_block: bool,
+		pub(crate) html_block: bool,
+		pub(crate) footnote_definition: bool,
+		pub(crate) link_definition: bool,
+		pub(crate) table: bool,
+		pub(crate) autolink: bool,
+		pub(crate) link: bool,
+		pub(crate) footnote_reference: bool,
+		pub(crate) reference_link: bool,
+		pub(crate) hard_newline: bool,
+		pub(crate) image: bool,
+		pub(crate) code_span: bool,
+		pub(crate) emphasis: bool,
+		pub(crate) strikethrough: bool
+	  },
+	  pub(crate) printer: nest! {
+		/// Line width the HTML printer wraps at
+		pub(crate) width: usize,
+		/// Prefix for footnote anchor ids (avoids clashes when several documents share a page)
+		pub(crate) anchor_prefix: String
+	  },
+	  pub(crate) heading: nest! {
+		/// Give every heading a slug `id` so sections can be deep-linked
+		pub(crate) anchors: bool,
+		/// Render the anchor as a visible permalink instead of an empty target
+		pub(crate) permalink: bool,
+		/// Link text of the permalink (raw HTML allowed)
+		pub(crate) permalink_symbol: String
+	  },
+	  pub(crate) alerts: nest! {
+		/// Built-in title language: `en`, `zh-TW`, `zh-CN`
+		pub(crate) locale: String,
+		/// Per-type title overrides keyed by lowercase type (`note`, `warning`, custom names)
+		pub(crate) titles: Option<HashMap<String, String>>
+	  },
+	  pub(crate) wiki_links: nest! {
+		/// Resolve `[[Page]]` / `[[Page|label]]` against `.md` files under `public_path`
+		pub(crate) enable: bool
+	  },
+	  pub(crate) math: nest! {
+		/// Convert `$...$` / `$$...$$` LaTeX to MathML on the server
+		pub(crate) enable: bool
+	  },
+	  pub(crate) links: nest! {
+		/// Open external links in a new tab (`target="_blank" rel="noopener noreferrer"`)
+		pub(crate) new_tab: bool,
+		/// Class added to external links, eg. for an icon (empty: none)
+		pub(crate) external_class: String,
+		/// Mark links to missing local `.md` files with the `broken-link` class
+		pub(crate) check_md: bool,
+		/// URL prefix → replacement; the longest matching prefix wins
+		pub(crate) rewrite: Option<HashMap<String, String>>
+	  },
+	  pub(crate) images: nest! {
+		/// `loading="lazy"` on every image
+		pub(crate) lazy: bool,
+		/// Intrinsic `width`/`height` on local images (prevents layout shift)
+		#[cfg(feature = "images")]
+		pub(crate) dimensions: bool,
+		#[cfg(feature = "images")]
+		pub(crate) variants: nest! {
+		  /// Offer downscaled copies via `srcset` (`img.png?w=480`), generated on first request
+		  pub(crate) enable: bool,
+		  /// Widths (px) to offer; only these are ever generated
+		  pub(crate) widths: Vec<u32>,
+		  /// Cache directory for variants and gallery thumbnails; unset = per-user cache directory
+		  pub(crate) cache_dir: Option<String>
+		}
+	  },
+	  pub(crate) include: nest! {
+		/// Expand `<!-- include: path.md -->` with the referenced file (confined to `public_path`)
+		pub(crate) enable: bool,
+		/// Maximum include nesting; deeper directives are left unexpanded
+		pub(crate) max_depth: usize
+	  },
+	  pub(crate) sanitize: nest! {
+		/// `allow`, `safe` or `escape` raw HTML in markdown
+		pub(crate) policy: HtmlPolicy
+	  },
+	  #[cfg(feature = "highlight")]
+	  pub(crate) highlight: nest! {
+		/// Enable server-side highlighting of fenced code blocks
+		pub(crate) enable: bool,
+		/// `class` (stylesheet served by the server) or `inline` (style attributes)
+		pub(crate) mode: HighlightMode,
+		/// Bundled syntect theme name (eg. `InspiredGitHub`)
+		pub(crate) theme: String
+	  }
+	},
+	/// File extension (lowercase, no dot) → renderer name (`markdown`); others are served as-is
+	#[serde(default = "default_renderers")]
+	pub(crate) renderers: HashMap<String, String>,
+	/// URL prefix → HTML file (relative to `public_path`) served with 200 for missing paths below
+	/// the prefix, for client-side routed apps
+	#[serde(default)]
+	pub(crate) spa: HashMap<String, String>,
+	/// Response headers added by every rule matching the request path (later rules win)
+	#[serde(default)]
+	pub(crate) headers: Vec<
+		nest! {
+		  /// Glob over the request path (relative to the site root); any path if unset
+		  #[serde(default)]
+		  pub(crate) path: Option<String>,
+		  /// Extensions (no dot, case-insensitive); any if empty
+		  #[serde(default)]
+		  pub(crate) ext: Vec<String>,
+		  /// Header name → value
+		  pub(crate) headers: HashMap<String, String>
+		},
+	>,
+	/// Redirect / rewrite rules checked in order before path resolution; the first match wins
+	#[serde(default)]
+	pub(crate) redirects: Vec<
+		nest! {
+		  /// Pattern matched against the (percent-encoded) request path
+		  pub(crate) from: String,
+		  /// Target path or URL; may use captures
+		  pub(crate) to: String,
+		  /// `exact`, `prefix`, `glob` or `regex`
+		  #[serde(default, rename = "match")]
+		  pub(crate) kind: RedirectMatch,
+		  /// 301, 302, 307 or 308
+		  #[serde(default = "default_redirect_status")]
+		  pub(crate) status: u16,
+		  /// Serve `to` internally instead of redirecting
+		  #[serde(default)]
+		  pub(crate) rewrite: bool,
+		  /// Append the request's query string to the redirect target
+		  #[serde(default = "default_true")]
+		  pub(crate) query: bool
+		},
+	>,
+	/// Directory pages
+	#[serde(default)]
+	pub(crate) directory: nest! {
+	  /// Default view; `?view=toc|list|gallery|media` overrides it per request
+	  pub(crate) view: DirectoryView,
+	  /// `auto`: share of files (0–1) that must be images / media to switch view
+	  pub(crate) ratio: f64,
+	  /// Gallery tile edge (px); thumbnails are generated at this size (feature: images)
+	  pub(crate) thumbnail_size: u32,
+	  /// Index file candidates, in order, served for any directory instead of a generated view
+	  pub(crate) index: Vec<String>
+	},
+	/// `source` renderer (`?view`)
+	#[serde(default)]
+	pub(crate) source: nest! {
+	  /// Lines rendered before the rest is left to `?raw`
+	  pub(crate) max_lines: usize
+	},
+	/// `csv` / `tsv` renderers
+	#[serde(default)]
+	pub(crate) table: nest! {
+	  /// `auto`, `always` or `never` treat the first row as column headers
+	  pub(crate) header: TableHeader,
+	  /// Rows per page (`?page=N`); large files are never rendered in one go
+	  pub(crate) page_size: usize,
+	  /// Click a column header to sort the current page
+	  pub(crate) sortable: bool
+	},
 	pub(crate) toc: nest! {
 	  /// File extensions to include in TOC generation
 	  pub(crate) ext: HashSet<String>,
-	  /// Directories to ignore in TOC generation
-	  pub(crate) ig: HashSet<String>
+	  /// Ignore patterns (gitignore syntax, relative to `public_path`); in order, the last match wins
+	  pub(crate) ig: Vec<String>,
+	  /// Per-directory ignore files read in addition to `ig` (`.gitignore`, `.tocignore`, ...)
+	  #[serde(default)]
+	  pub(crate) ig_files: Vec<String>,
+	  /// Also answer 404 for ignored paths instead of only hiding them
+	  #[serde(default)]
+	  pub(crate) ig_deny: bool,
+	  /// Label markdown entries with their front matter `title` / first H1 instead of the file name
+	  #[serde(default)]
+	  pub(crate) titles: bool,
+	  /// Entry order: `name`, `title`, `order` (front matter `order`/`weight`) or `mtime`
+	  #[serde(default)]
+	  pub(crate) sort: TocSort,
+	  /// Levels listed (1 = direct children only); `0` is unlimited
+	  #[serde(default)]
+	  pub(crate) depth: usize,
+	  /// Render directories as collapsible `<details>` instead of one expanded list
+	  #[serde(default)]
+	  pub(crate) collapse: bool,
+	  /// File names (first existing wins) rendered above a directory's TOC
+	  #[serde(default)]
+	  pub(crate) readme: Vec<String>
 	},
 	/// Path to the public directory
 	pub(crate) public_path: String,
@@ -145,6 +422,42 @@ impl Default for CofgTemplating {
 	}
 }
 
+impl Default for CofgMarkdown {
+	fn default() -> Self {
+		Cofg::default().markdown
+	}
+}
+
+impl Default for CofgDirectory {
+	fn default() -> Self {
+		Cofg::default().directory
+	}
+}
+
+impl Default for CofgSource {
+	fn default() -> Self {
+		Cofg::default().source
+	}
+}
+
+impl Default for CofgTable {
+	fn default() -> Self {
+		Cofg::default().table
+	}
+}
+
+fn default_renderers() -> HashMap<String, String> {
+	Cofg::default().renderers
+}
+
+fn default_redirect_status() -> u16 {
+	301
+}
+
+fn default_true() -> bool {
+	true
+}
+
 impl Cofg {
 	/// Get XDG config directory paths for my-http-server.
 	///
@@ -390,11 +703,11 @@ impl Cofg {
 						.map(String::from),
 				);
 			}
-			if self.toc.ig.contains(marker) {
-				self.toc.ig.remove(marker);
+			// Expanded in place: the order of gitignore rules matters.
+			if let Some(i) = self.toc.ig.iter().position(|ig| ig == marker) {
 				self.toc
 					.ig
-					.extend(["node_modules"].into_iter().map(String::from));
+					.splice(i..=i, ["node_modules"].into_iter().map(String::from));
 			}
 		}
 		self
@@ -0,0 +1,81 @@
+//! Per-path response headers (`headers`)
+//!
+//! WHY: Hashed assets want long-lived caching, internal runbooks want `no-store`, downloads want
+//! `Content-Disposition`; none of that could be set. Every rule whose `path` glob and `ext` list
+//! match the served path adds its headers to the `main_req` response, whatever produced it (static
+//! file, rendered page, directory page, redirect or 404). The served path is the request path, or
+//! the target of a rewrite or `spa` fallback. Later rules override earlier ones, and rule headers
+//! replace those set by the handler.
+//!
+//! 中文：依路徑 glob 或副檔名為回應加上自訂標頭（快取、CSP、X-Robots-Tag 等），適用於所有回應。
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use actix_web::http::header::{HeaderName, HeaderValue};
+use log::warn;
+use wax::{Glob, Program as _};
+
+use crate::cofg::config::Cofg;
+
+/// Compiled `path` globs; `None` if invalid (warned once).
+static GLOBS: LazyLock<RwLock<HashMap<String, Option<Arc<Glob<'static>>>>>> =
+	LazyLock::new(|| RwLock::new(HashMap::new()));
+
+fn glob(pattern: &str) -> Option<Arc<Glob<'static>>> {
+	if let Ok(cache) = GLOBS.read()
+		&& let Some(glob) = cache.get(pattern)
+	{
+		return glob.clone();
+	}
+	let glob = Glob::new(pattern.trim_start_matches('/'))
+		.inspect_err(|e| warn!("headers: `{pattern}`: {e}"))
+		.ok()
+		.map(|g| Arc::new(g.into_owned()));
+	if let Ok(mut cache) = GLOBS.write() {
+		cache.insert(pattern.to_string(), glob.clone());
+	}
+	glob
+}
+
+/// Headers for the percent-decoded request path `path`, in rule order (later wins on insert).
+pub(crate) fn for_path(path: &str, c: &Cofg) -> Vec<(HeaderName, HeaderValue)> {
+	let path = path.trim_start_matches('/');
+	let ext = Path::new(path)
+		.extension()
+		.and_then(|e| e.to_str())
+		.map(str::to_ascii_lowercase);
+	let mut out = Vec::new();
+	for rule in &c.headers {
+		let path_ok = rule
+			.path
+			.as_deref()
+			.is_none_or(|p| glob(p).is_some_and(|g| g.is_match(path)));
+		let ext_ok = rule.ext.is_empty()
+			|| ext
+				.as_deref()
+				.is_some_and(|ext| rule.ext.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+		if !(path_ok && ext_ok) {
+			continue;
+		}
+		for (name, value) in &rule.headers {
+			match (
+				HeaderName::try_from(name.as_str()),
+				HeaderValue::try_from(value.as_str()),
+			) {
+				(Ok(name), Ok(value)) => out.push((name, value)),
+				_ => warn!("headers: invalid header `{name}: {value}`"),
+			}
+		}
+	}
+	out
+}
+
+/// Add the headers of [`for_path`] to `res`.
+pub(crate) fn apply(path: &str, res: &mut actix_web::HttpResponse, c: &Cofg) {
+	let headers = res.headers_mut();
+	for (name, value) in for_path(path, c) {
+		headers.insert(name, value);
+	}
+}
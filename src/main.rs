@@ -10,7 +10,9 @@ mod cofg;
 mod parser;
 use crate::cofg::{cli, config::Cofg};
 mod error;
+mod headers;
 use crate::error::AppResult;
+mod redirect;
 mod request;
 use crate::request::main_req;
 
@@ -467,6 +469,10 @@ fn build_server(s: &Cofg) -> AppResult<Server> {
 					.service(api::file::check_exists),
 			);
 		}
+		#[cfg(feature = "highlight")]
+		{
+			app = app.service(request::highlight_css);
+		}
 		app = app.service(main_req);
 		app
 	})
@@ -0,0 +1,112 @@
+//! GitHub-style alerts (`> [!NOTE]`, `> [!WARNING]`, ...)
+//!
+//! WHY: markdown_ppp parses alerts but prints fixed English titles. We emit the same markup GitHub
+//! does (`markdown-alert markdown-alert-<type>`, octicon + title) so github-markdown-css styles it,
+//! with titles taken from `markdown.alerts` (locale preset + per-type overrides). Inner blocks stay
+//! in the AST, wrapped by raw open/close `HtmlBlock`s, so later stages still see them.
+//!
+//! 中文：將 GitHub 提示區塊轉為與 GitHub 相同的樣式，標題可設定（內建中文）。
+
+use markdown_ppp::ast::{Block, GitHubAlertType};
+
+use crate::cofg::config::CofgMarkdownAlerts;
+
+// Octicons (MIT, github.com/primer/octicons), same icons GitHub renders for alerts.
+const ICON_INFO: &str = r#"<svg class="octicon octicon-info mr-2" viewBox="0 0 16 16" version="1.1" width="16" height="16" aria-hidden="true"><path d="M0 8a8 8 0 1 1 16 0A8 8 0 0 1 0 8Zm8-6.5a6.5 6.5 0 1 0 0 13 6.5 6.5 0 0 0 0-13ZM6.5 7.75A.75.75 0 0 1 7.25 7h1a.75.75 0 0 1 .75.75v2.75h.25a.75.75 0 0 1 0 1.5h-2a.75.75 0 0 1 0-1.5h.25v-2h-.25a.75.75 0 0 1-.75-.75ZM8 6a1 1 0 1 1 0-2 1 1 0 0 1 0 2Z"></path></svg>"#;
+const ICON_LIGHT_BULB: &str = r#"<svg class="octicon octicon-light-bulb mr-2" viewBox="0 0 16 16" version="1.1" width="16" height="16" aria-hidden="true"><path d="M8 1.5c-2.363 0-4 1.69-4 3.75 0 .984.424 1.625.984 2.304l.214.253c.223.264.47.556.673.848.284.411.537.896.621 1.49a.75.75 0 0 1-1.484.211c-.04-.282-.163-.547-.37-.847a8.456 8.456 0 0 0-.542-.68c-.084-.1-.173-.205-.268-.32C3.201 7.75 2.5 6.766 2.5 5.25 2.5 2.31 4.863 0 8 0s5.5 2.31 5.5 5.25c0 1.516-.701 2.5-1.328 3.259-.095.115-.184.22-.268.319-.207.245-.383.453-.541.681-.208.3-.33.565-.37.847a.751.751 0 0 1-1.485-.212c.084-.593.337-1.078.621-1.489.203-.292.45-.584.673-.848.075-.088.147-.173.213-.253.561-.679.985-1.32.985-2.304 0-2.06-1.637-3.75-4-3.75ZM5.75 12h4.5a.75.75 0 0 1 0 1.5h-4.5a.75.75 0 0 1 0-1.5ZM6 15.25a.75.75 0 0 1 .75-.75h2.5a.75.75 0 0 1 0 1.5h-2.5a.75.75 0 0 1-.75-.75Z"></path></svg>"#;
+const ICON_REPORT: &str = r#"<svg class="octicon octicon-report mr-2" viewBox="0 0 16 16" version="1.1" width="16" height="16" aria-hidden="true"><path d="M0 1.75C0 .784.784 0 1.75 0h12.5C15.216 0 16 .784 16 1.75v9.5A1.75 1.75 0 0 1 14.25 13H8.06l-2.573 2.573A1.458 1.458 0 0 1 3 14.543V13H1.75A1.75 1.75 0 0 1 0 11.25Zm1.75-.25a.25.25 0 0 0-.25.25v9.5c0 .138.112.25.25.25h2a.75.75 0 0 1 .75.75v2.19l2.72-2.72a.749.749 0 0 1 .53-.22h6.5a.25.25 0 0 0 .25-.25v-9.5a.25.25 0 0 0-.25-.25Zm7 2.25v2.5a.75.75 0 0 1-1.5 0v-2.5a.75.75 0 0 1 1.5 0ZM9 9a1 1 0 1 1-2 0 1 1 0 0 1 2 0Z"></path></svg>"#;
+const ICON_ALERT: &str = r#"<svg class="octicon octicon-alert mr-2" viewBox="0 0 16 16" version="1.1" width="16" height="16" aria-hidden="true"><path d="M6.457 1.047c.659-1.234 2.427-1.234 3.086 0l6.082 11.378A1.75 1.75 0 0 1 14.082 15H1.918a1.75 1.75 0 0 1-1.543-2.575Zm1.763.707a.25.25 0 0 0-.44 0L1.698 13.132a.25.25 0 0 0 .22.368h12.164a.25.25 0 0 0 .22-.368Zm.53 3.996v2.5a.75.75 0 0 1-1.5 0v-2.5a.75.75 0 0 1 1.5 0ZM9 11a1 1 0 1 1-2 0 1 1 0 0 1 2 0Z"></path></svg>"#;
+const ICON_STOP: &str = r#"<svg class="octicon octicon-stop mr-2" viewBox="0 0 16 16" version="1.1" width="16" height="16" aria-hidden="true"><path d="M4.47.22A.749.749 0 0 1 5 0h6c.199 0 .389.079.53.22l4.25 4.25c.141.14.22.331.22.53v6a.749.749 0 0 1-.22.53l-4.25 4.25A.749.749 0 0 1 11 16H5a.749.749 0 0 1-.53-.22L.22 11.53A.749.749 0 0 1 0 11V5c0-.199.079-.389.22-.53Zm.84 1.28L1.5 5.31v5.38l3.81 3.81h5.38l3.81-3.81V5.31L10.69 1.5ZM8 4a.75.75 0 0 1 .75.75v3.5a.75.75 0 0 1-1.5 0v-3.5A.75.75 0 0 1 8 4Zm0 8a1 1 0 1 1 0-2 1 1 0 0 1 0 2Z"></path></svg>"#;
+
+/// Lowercase type key used for CSS class and `titles` lookup (`note`, `warning`, custom names).
+fn kind(t: &GitHubAlertType) -> String {
+	match t {
+		GitHubAlertType::Note => "note".into(),
+		GitHubAlertType::Tip => "tip".into(),
+		GitHubAlertType::Important => "important".into(),
+		GitHubAlertType::Warning => "warning".into(),
+		GitHubAlertType::Caution => "caution".into(),
+		GitHubAlertType::Custom(name) => name
+			.chars()
+			.filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+			.collect::<String>()
+			.to_ascii_lowercase(),
+	}
+}
+
+/// Built-in title for `kind` in `locale`; unknown locales fall back to English.
+fn preset_title(locale: &str, kind: &str) -> Option<&'static str> {
+	let titles = match locale {
+		"zh-TW" | "zh-Hant" => ["備註", "提示", "重要", "警告", "注意"],
+		"zh-CN" | "zh-Hans" => ["备注", "提示", "重要", "警告", "注意"],
+		_ => ["Note", "Tip", "Important", "Warning", "Caution"],
+	};
+	let idx = ["note", "tip", "important", "warning", "caution"]
+		.iter()
+		.position(|k| *k == kind)?;
+	Some(titles[idx])
+}
+
+/// Title for an alert: `titles` override → locale preset → the marker name itself.
+pub(crate) fn title(c: &CofgMarkdownAlerts, t: &GitHubAlertType) -> String {
+	let kind = kind(t);
+	if let Some(title) = c.titles.as_ref().and_then(|m| m.get(&kind)) {
+		return title.clone();
+	}
+	match (preset_title(&c.locale, &kind), t) {
+		(Some(title), _) => title.to_string(),
+		(None, GitHubAlertType::Custom(name)) => handlebars::html_escape(name),
+		(None, _) => kind,
+	}
+}
+
+fn open_tag(c: &CofgMarkdownAlerts, t: &GitHubAlertType) -> String {
+	let icon = match t {
+		GitHubAlertType::Tip => ICON_LIGHT_BULB,
+		GitHubAlertType::Important => ICON_REPORT,
+		GitHubAlertType::Warning => ICON_ALERT,
+		GitHubAlertType::Caution => ICON_STOP,
+		GitHubAlertType::Note | GitHubAlertType::Custom(_) => ICON_INFO,
+	};
+	format!(
+		r#"<div class="markdown-alert markdown-alert-{}"><p class="markdown-alert-title">{icon}{}</p>"#,
+		kind(t),
+		title(c, t)
+	)
+}
+
+fn expand(c: &CofgMarkdownAlerts, block: Block) -> Vec<Block> {
+	let walk = |blocks: Vec<Block>| -> Vec<Block> {
+		blocks.into_iter().flat_map(|b| expand(c, b)).collect()
+	};
+	match block {
+		Block::GitHubAlert(alert) => {
+			let mut out = vec![Block::HtmlBlock(open_tag(c, &alert.alert_type))];
+			out.extend(walk(alert.blocks));
+			out.push(Block::HtmlBlock("</div>".to_string()));
+			out
+		}
+		Block::BlockQuote(blocks) => vec![Block::BlockQuote(walk(blocks))],
+		Block::List(mut list) => {
+			for item in &mut list.items {
+				item.blocks = walk(std::mem::take(&mut item.blocks));
+			}
+			vec![Block::List(list)]
+		}
+		Block::FootnoteDefinition(mut def) => {
+			def.blocks = walk(def.blocks);
+			vec![Block::FootnoteDefinition(def)]
+		}
+		other => vec![other],
+	}
+}
+
+/// Replace every `GitHubAlert` (at any depth) with GitHub-compatible callout markup.
+pub(crate) fn render(
+	doc: markdown_ppp::ast::Document,
+	c: &CofgMarkdownAlerts,
+) -> markdown_ppp::ast::Document {
+	markdown_ppp::ast::Document {
+		blocks: doc.blocks.into_iter().flat_map(|b| expand(c, b)).collect(),
+	}
+}
@@ -0,0 +1,248 @@
+//! Directory pages: which view to show, and the classic index (`list`)
+//!
+//! WHY: The markdown TOC (`toc`) lists only files matching `toc.ext`, recursively, as bullets;
+//! anything else is servable but invisible. The `list` view is an autoindex-style table of every
+//! direct child (name, size, modification time, type) with a parent link and sortable columns.
+//! [`choose`] picks between it, the TOC and the media views of [`super::media`].
+//!
+//! 中文：目錄頁檢視選擇；list 為傳統目錄索引（大小、修改時間、類型，可排序）。
+
+use std::path::Path;
+
+use handlebars::html_escape;
+use percent_encoding::utf8_percent_encode;
+
+use crate::cofg::config::{Cofg, DirectoryView};
+use crate::error::AppResult;
+
+use super::{
+	PageContext,
+	markdown::{NON_ALPHANUMERIC, get_toc, get_toc_html},
+	markdown_body,
+	media::{MediaKind, kind},
+	renderer::Rendered,
+};
+
+/// Direct children of a directory, sorted by name.
+#[derive(Debug, Default)]
+pub(crate) struct Listing {
+	pub(crate) dirs: Vec<String>,
+	pub(crate) files: Vec<String>,
+}
+
+/// List `dir`, skipping dotfiles and ignored paths (see [`super::ignore`]).
+pub(crate) fn listing(dir: &Path, c: &Cofg) -> AppResult<Listing> {
+	let public_path = Path::new(&c.public_path)
+		.canonicalize()
+		.unwrap_or_else(|_| dir.to_path_buf());
+	let mut ignore = super::ignore::Matcher::new(&public_path, c);
+	let mut listing = Listing::default();
+	for entry in std::fs::read_dir(dir)? {
+		let entry = entry?;
+		let name = entry.file_name().to_string_lossy().into_owned();
+		let is_dir = entry.path().is_dir();
+		if name.starts_with('.') || ignore.is_ignored(&entry.path(), is_dir) {
+			continue;
+		}
+		if is_dir {
+			listing.dirs.push(name);
+		} else {
+			listing.files.push(name);
+		}
+	}
+	listing.dirs.sort();
+	listing.files.sort();
+	Ok(listing)
+}
+
+/// First `directory.index` candidate that is a file in `dir`.
+pub(crate) fn index_file(dir: &Path, c: &Cofg) -> Option<std::path::PathBuf> {
+	c.directory
+		.index
+		.iter()
+		.map(|name| dir.join(name))
+		.find(|path| path.is_file())
+}
+
+/// View for this request: `?view=` if valid, else `directory.view` (`auto` resolved by ratio,
+/// falling back to `toc`).
+pub(crate) fn choose(listing: &Listing, c: &Cofg, requested: Option<&str>) -> DirectoryView {
+	let view = requested
+		.and_then(|v| serde_json::from_value(serde_json::Value::String(v.into())).ok())
+		.unwrap_or(c.directory.view);
+	if view != DirectoryView::Auto {
+		return view;
+	}
+	if listing.files.is_empty() {
+		return DirectoryView::Toc;
+	}
+	let share = |pred: fn(MediaKind) -> bool| {
+		let n = listing
+			.files
+			.iter()
+			.filter(|f| kind(Path::new(f)).is_some_and(pred))
+			.count();
+		n as f64 / listing.files.len() as f64
+	};
+	if share(|k| k == MediaKind::Image) >= c.directory.ratio {
+		DirectoryView::Gallery
+	} else if share(|k| k != MediaKind::Image) >= c.directory.ratio {
+		DirectoryView::Media
+	} else {
+		DirectoryView::Toc
+	}
+}
+
+/// Absolute, percent-encoded URL of `name` inside `rel`.
+pub(crate) fn url(rel: &Path, name: &str) -> String {
+	let path = rel.join(name).to_string_lossy().replace('\\', "/");
+	format!("/{}", utf8_percent_encode(&path, NON_ALPHANUMERIC))
+}
+
+/// `<h1>` plus the links switching between views.
+pub(crate) fn header(title: &str) -> String {
+	format!(
+		"<h1>{}</h1>\n{}\n",
+		html_escape(title),
+		r#"<p class="directory-views"><a href="?view=toc">toc</a> · <a href="?view=list">list</a> · <a href="?view=gallery">gallery</a> · <a href="?view=media">media</a></p>"#
+	)
+}
+
+/// The `toc` view of `dir_abs`: its README (first of `toc.readme`) above the TOC, which is
+/// collapsible with `toc.collapse`.
+///
+/// README values (front matter, outline, ...) override the TOC's so the page describes the README.
+pub(crate) fn render_toc(
+	title: &str,
+	dir_abs: &Path,
+	c: &Cofg,
+	page: &PageContext,
+) -> AppResult<Rendered> {
+	let mut rendered = if c.toc.collapse {
+		Rendered {
+			body: format!(
+				"<h1>{}</h1>\n{}",
+				html_escape(title),
+				get_toc_html(dir_abs, c)?
+			),
+			values: serde_json::Map::new(),
+		}
+	} else {
+		markdown_body(get_toc(dir_abs, c, Some(title.to_string()))?, c, page)?
+	};
+	let readme = c
+		.toc
+		.readme
+		.iter()
+		.map(|name| dir_abs.join(name))
+		.find(|path| path.is_file());
+	if let Some(readme) = readme {
+		let page = PageContext {
+			source: Some(readme.clone()),
+			..page.clone()
+		};
+		let doc = markdown_body(std::fs::read_to_string(&readme)?, c, &page)?;
+		rendered.body = format!(
+			"<article class=\"directory-readme\">\n{}</article>\n{}",
+			doc.body, rendered.body
+		);
+		rendered.values.extend(doc.values);
+	}
+	Ok(rendered)
+}
+
+/// `1.5 KiB`-style size.
+fn human_size(bytes: u64) -> String {
+	const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+	let mut size = bytes as f64;
+	let mut unit = 0;
+	while size >= 1024.0 && unit < UNITS.len() - 1 {
+		size /= 1024.0;
+		unit += 1;
+	}
+	if unit == 0 {
+		format!("{bytes} B")
+	} else {
+		format!("{size:.1} {}", UNITS[unit])
+	}
+}
+
+/// Classic index of directory `dir_abs` (`rel` relative to `public_path`).
+///
+/// Cells carry `data-sort` keys (bytes, Unix seconds, directories first) so the template's sort
+/// script orders by value rather than by the displayed text.
+pub(crate) fn render_index(title: &str, dir_abs: &Path, rel: &Path, listing: &Listing) -> Rendered {
+	let mut body = header(title);
+	if let Some(parent) = rel.parent() {
+		let parent = parent.to_string_lossy().replace('\\', "/");
+		body.push_str(&format!(
+			r#"<p class="directory-parent"><a href="/{}">../</a></p>"#,
+			html_escape(&utf8_percent_encode(&parent, NON_ALPHANUMERIC).to_string())
+		));
+		body.push('\n');
+	}
+	body.push_str(
+		"<table class=\"directory-index sortable\">\n<thead><tr><th>Name</th><th>Size</th><th>Modified</th><th>Type</th></tr></thead>\n<tbody>\n",
+	);
+	let entries = listing
+		.dirs
+		.iter()
+		.map(|d| (d, true))
+		.chain(listing.files.iter().map(|f| (f, false)));
+	for (name, is_dir) in entries {
+		let metadata = std::fs::metadata(dir_abs.join(name)).ok();
+		let (size, size_key) = match &metadata {
+			Some(m) if !is_dir => (human_size(m.len()), m.len() as i64),
+			_ => ("—".to_string(), -1),
+		};
+		let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+		let (modified, modified_key) = match modified {
+			Some(t) => (
+				actix_web::http::header::HttpDate::from(t).to_string(),
+				t.duration_since(std::time::UNIX_EPOCH)
+					.map(|d| d.as_secs())
+					.unwrap_or_default(),
+			),
+			None => (String::new(), 0),
+		};
+		let mime = if is_dir {
+			"directory".to_string()
+		} else {
+			Path::new(name)
+				.extension()
+				.and_then(|e| e.to_str())
+				.map(|e| {
+					actix_files::file_extension_to_mime(e)
+						.essence_str()
+						.to_string()
+				})
+				.unwrap_or_else(|| "application/octet-stream".into())
+		};
+		let label = if is_dir {
+			format!("{name}/")
+		} else {
+			name.clone()
+		};
+		body.push_str(&format!(
+			r#"<tr><td data-sort="{}{}"><a href="{}">{}</a></td><td data-sort="{size_key}">{size}</td><td data-sort="{modified_key}">{modified}</td><td>{mime}</td></tr>"#,
+			u8::from(!is_dir),
+			html_escape(name),
+			html_escape(&url(rel, name)),
+			html_escape(&label),
+		));
+		body.push('\n');
+	}
+	body.push_str("</tbody>\n</table>\n");
+
+	let mut values = serde_json::Map::new();
+	values.insert(
+		"directory".into(),
+		serde_json::json!({
+			"view": DirectoryView::List,
+			"dirs": listing.dirs.len(),
+			"files": listing.files.len(),
+			"sortable": true,
+		}),
+	);
+	Rendered { body, values }
+}
@@ -0,0 +1,79 @@
+//! YAML front matter (`---` ... `---` at the very top of a page)
+//!
+//! WHY: Content trees need different markdown dialects; a page can override the `markdown` config
+//! section for itself. The overlay goes through the same `config` crate layering as `cofg.yaml`
+//! (site section as base, page values on top), so partial overrides and type errors behave alike.
+//! Pages may only change parsing, printing and on/off toggles. Keys that are emitted as raw HTML
+//! after `sanitize` has run (`heading.permalink_symbol`, `alerts.titles`, `links.external_class`)
+//! or that reach other resources (`include`, `images`, `links.rewrite`) stay site-only, as does
+//! `sanitize` itself: those settings exist to protect readers and the server from page authors.
+//!
+//! 中文：解析頁首 YAML front matter，可逐頁覆寫 markdown 設定；sanitize、include、images 及會以原始
+//! HTML 輸出的設定僅限站台設定。
+
+use handlebars::JsonValue;
+use serde_json::Map;
+
+use crate::cofg::config::CofgMarkdown;
+
+/// Split `md` into front matter YAML and body, if it starts with a `---` line closed by `---`/`...`.
+fn split(md: &str) -> Option<(&str, &str)> {
+	let rest = md
+		.strip_prefix("---\n")
+		.or_else(|| md.strip_prefix("---\r\n"))?;
+	let mut offset = 0;
+	for line in rest.split_inclusive('\n') {
+		if matches!(line.trim_end(), "---" | "...") {
+			return Some((&rest[..offset], &rest[offset + line.len()..]));
+		}
+		offset += line.len();
+	}
+	None
+}
+
+fn parse(yaml: &str) -> crate::error::AppResult<Map<String, JsonValue>> {
+	Ok(config::Config::builder()
+		.add_source(config::File::from_str(yaml, config::FileFormat::Yaml))
+		.build()?
+		.try_deserialize()?)
+}
+
+/// Cut front matter off `md`.
+///
+/// Only a YAML *mapping* counts: a leading `---` followed by prose (thematic break, setext heading)
+/// is left alone and yields an empty map.
+pub(crate) fn extract(md: String) -> (Map<String, JsonValue>, String) {
+	let Some((yaml, body)) = split(&md) else {
+		return (Map::new(), md);
+	};
+	match parse(yaml) {
+		Ok(values) => {
+			let body = body.to_string();
+			(values, body)
+		}
+		Err(e) => {
+			log::debug!("not front matter: {e}");
+			(Map::new(), md)
+		}
+	}
+}
+
+/// `site` with the page's `markdown:` front matter layered on top (site-only keys kept).
+pub(crate) fn overlay(
+	site: &CofgMarkdown,
+	page: &JsonValue,
+) -> crate::error::AppResult<CofgMarkdown> {
+	let mut merged: CofgMarkdown = config::Config::builder()
+		.add_source(config::Config::try_from(site)?)
+		.add_source(config::Config::try_from(page)?)
+		.build()?
+		.try_deserialize()?;
+	merged.sanitize = site.sanitize.clone();
+	merged.include = site.include.clone();
+	merged.images = site.images.clone();
+	merged.heading.permalink_symbol = site.heading.permalink_symbol.clone();
+	merged.alerts.titles = site.alerts.titles.clone();
+	merged.links.external_class = site.links.external_class.clone();
+	merged.links.rewrite = site.links.rewrite.clone();
+	Ok(merged)
+}
@@ -0,0 +1,154 @@
+//! Heading anchors & page outline
+//!
+//! WHY: `markdown_ppp::html_printer` emits bare `<hN>` tags, so sections cannot be deep-linked.
+//! Injecting an anchor element as the heading's first inline keeps the stock printer while giving
+//! every heading a stable `id` (`heading.anchors`, opt-in so existing output is unchanged); the
+//! same pass records the `outline` for on-page TOCs, with ids only when the anchors exist.
+//!
+//! 中文：為標題產生穩定 id（支援中日韓文字）並收集大綱供模板使用。
+
+use std::collections::HashMap;
+
+use markdown_ppp::ast::{Block, Heading, HeadingKind, Inline, SetextHeading};
+use serde::Serialize;
+
+use crate::cofg::config::CofgMarkdownHeading;
+
+/// One heading in the `outline` tree exposed to templates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct OutlineEntry {
+	/// 1..=6
+	pub(crate) level: u8,
+	pub(crate) text: String,
+	/// Slug used as the anchor `id` (link with `#{{id}}`); absent without `heading.anchors`, since
+	/// nothing on the page carries it then
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub(crate) id: Option<String>,
+	pub(crate) children: Vec<OutlineEntry>,
+}
+
+/// GitHub-style slug: lowercase, keep letters/digits (any script, so CJK survives), `-` and `_`,
+/// whitespace becomes `-`, everything else is dropped.
+pub(crate) fn slugify(text: &str) -> String {
+	let mut slug = String::with_capacity(text.len());
+	for ch in text.trim().chars() {
+		if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+			slug.extend(ch.to_lowercase());
+		} else if ch.is_whitespace() {
+			slug.push('-');
+		}
+	}
+	slug
+}
+
+/// Flatten inline content to the text a reader sees (no markup, no raw HTML).
+pub(crate) fn plain_text(inlines: &[Inline]) -> String {
+	let mut out = String::new();
+	for inline in inlines {
+		match inline {
+			Inline::Text(t) | Inline::Code(t) | Inline::Autolink(t) => out.push_str(t),
+			Inline::Emphasis(c) | Inline::Strong(c) | Inline::Strikethrough(c) => {
+				out.push_str(&plain_text(c))
+			}
+			Inline::Link(l) => out.push_str(&plain_text(&l.children)),
+			Inline::LinkReference(l) => out.push_str(&plain_text(&l.text)),
+			Inline::Image(i) => out.push_str(&i.alt),
+			Inline::LineBreak => out.push(' '),
+			Inline::Html(_) | Inline::FootnoteReference(_) | Inline::Empty => {}
+		}
+	}
+	out
+}
+
+fn heading_level(kind: &HeadingKind) -> u8 {
+	match kind {
+		HeadingKind::Atx(n) => (*n).clamp(1, 6),
+		HeadingKind::Setext(SetextHeading::Level1) => 1,
+		HeadingKind::Setext(SetextHeading::Level2) => 2,
+	}
+}
+
+/// Transformer assigning unique slugs to every heading (including those nested in quotes/lists).
+///
+/// Duplicate slugs get `-1`, `-2`, … suffixes in document order, matching GitHub.
+pub(crate) struct HeadingAnchors<'a> {
+	cofg: &'a CofgMarkdownHeading,
+	seen: HashMap<String, usize>,
+	/// Flat `(level, text, id)` list in document order; see [`HeadingAnchors::outline`].
+	headings: Vec<(u8, String, String)>,
+}
+
+impl<'a> HeadingAnchors<'a> {
+	pub(crate) fn new(cofg: &'a CofgMarkdownHeading) -> Self {
+		Self {
+			cofg,
+			seen: HashMap::new(),
+			headings: Vec::new(),
+		}
+	}
+
+	fn unique_slug(&mut self, text: &str) -> String {
+		let mut base = slugify(text);
+		if base.is_empty() {
+			base = "section".to_string();
+		}
+		let mut slug = base.clone();
+		while let Some(n) = self.seen.get_mut(&slug) {
+			*n += 1;
+			slug = format!("{base}-{n}");
+		}
+		self.seen.insert(slug.clone(), 0);
+		slug
+	}
+
+	/// Nest the collected headings by level (a heading owns every following deeper heading).
+	pub(crate) fn outline(&self) -> Vec<OutlineEntry> {
+		fn insert(list: &mut Vec<OutlineEntry>, entry: OutlineEntry) {
+			match list.last_mut() {
+				Some(last) if last.level < entry.level => insert(&mut last.children, entry),
+				_ => list.push(entry),
+			}
+		}
+		let mut root = Vec::new();
+		for (level, text, id) in &self.headings {
+			insert(
+				&mut root,
+				OutlineEntry {
+					level: *level,
+					text: text.clone(),
+					id: self.cofg.anchors.then(|| id.clone()),
+					children: Vec::new(),
+				},
+			);
+		}
+		root
+	}
+}
+
+impl markdown_ppp::ast_transform::Transformer for HeadingAnchors<'_> {
+	fn transform_block(&mut self, block: Block) -> Block {
+		match block {
+			Block::Heading(Heading { kind, content }) => {
+				let text = plain_text(&content);
+				let id = self.unique_slug(&text);
+				self.headings
+					.push((heading_level(&kind), text.trim().to_string(), id.clone()));
+
+				let anchor = if self.cofg.permalink {
+					format!(
+						r##"<a class="anchor" id="{id}" href="#{id}" aria-hidden="true">{}</a>"##,
+						self.cofg.permalink_symbol
+					)
+				} else {
+					format!(r#"<a id="{id}"></a>"#)
+				};
+				let mut content = content;
+				if self.cofg.anchors {
+					content.insert(0, Inline::Html(anchor));
+				}
+				Block::Heading(Heading { kind, content })
+			}
+			other => self.walk_transform_block(other),
+		}
+	}
+}
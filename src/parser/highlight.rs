@@ -0,0 +1,190 @@
+//! Server-side syntax highlighting (feature: highlight)
+//!
+//! WHY: `markdown_ppp` prints fenced code as plain `<pre><code>`; highlighting on the server keeps
+//! pages JS-free. Fenced blocks with a known language are replaced by pre-rendered `HtmlBlock`s,
+//! anything else falls through to the stock printer unchanged.
+//!
+//! 中文：伺服器端程式碼高亮；未知語言維持原樣輸出。
+
+use std::sync::OnceLock;
+
+use markdown_ppp::ast::{Block, CodeBlock, CodeBlockKind};
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::cofg::config::{CofgMarkdownHighlight, HighlightMode};
+
+/// Route serving the stylesheet for `mode: class`.
+pub(crate) const CSS_PATH: &str = "/__highlight.css";
+
+/// Prefix keeps syntect's scope classes (`string`, `keyword`, ...) clear of page CSS.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+// PERF: the bundled dumps take a noticeable moment to decode; do it once, on first use.
+fn syntaxes() -> &'static SyntaxSet {
+	SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme(name: &str) -> Option<&'static Theme> {
+	THEMES.get_or_init(ThemeSet::load_defaults).themes.get(name)
+}
+
+/// Stylesheet for the configured theme, or `None` if the theme is unknown.
+pub(crate) fn theme_css(c: &CofgMarkdownHighlight) -> Option<String> {
+	let theme = theme(&c.theme)?;
+	syntect::html::css_for_theme_with_class_style(theme, CLASS_STYLE)
+		.inspect_err(|e| log::warn!("highlight css for {}: {e}", c.theme))
+		.ok()
+}
+
+/// First word of the fence info string, restricted to characters safe inside a class attribute.
+fn language(info: &str) -> Option<&str> {
+	let lang = info.split_whitespace().next()?;
+	lang.chars()
+		.all(|ch| ch.is_ascii_alphanumeric() || "+-#._".contains(ch))
+		.then_some(lang)
+}
+
+/// Highlight `code` as `lang`; `None` when the language is unknown or highlighting fails.
+pub(crate) fn highlight(code: &str, lang: &str, c: &CofgMarkdownHighlight) -> Option<String> {
+	let ss = syntaxes();
+	let syntax = ss.find_syntax_by_token(lang)?;
+	match c.mode {
+		HighlightMode::Class => {
+			let mut html_gen = ClassedHTMLGenerator::new_with_class_style(syntax, ss, CLASS_STYLE);
+			for line in LinesWithEndings::from(code) {
+				if let Err(e) = html_gen.parse_html_for_line_which_includes_newline(line) {
+					log::warn!("highlight {lang}: {e}");
+					return None;
+				}
+			}
+			Some(format!(
+				r#"<pre class="hl-code"><code class="language-{lang}">{}</code></pre>"#,
+				html_gen.finalize()
+			))
+		}
+		HighlightMode::Inline => {
+			let Some(theme) = theme(&c.theme) else {
+				log::warn!("unknown highlight theme: {}", c.theme);
+				return None;
+			};
+			syntect::html::highlighted_html_for_string(code, ss, syntax, theme)
+				.inspect_err(|e| log::warn!("highlight {lang}: {e}"))
+				.ok()
+		}
+	}
+}
+
+/// Syntax for a file: by extension/name `token`, else by its first line (shebang, modeline).
+fn syntax_for(token: &str, code: &str) -> Option<&'static SyntaxReference> {
+	let ss = syntaxes();
+	ss.find_syntax_by_token(token)
+		.or_else(|| ss.find_syntax_by_first_line(code))
+}
+
+/// Highlight a whole file for the source viewer, one self-contained HTML fragment per line
+/// (newline stripped) so each line can carry its own anchor, plus the `<pre>` style (theme
+/// background) in inline mode.
+///
+/// Class mode re-opens the spans still open from the previous line (block comments, multi-line
+/// strings) and closes them again at the line end. `None` when no syntax matches or highlighting
+/// fails.
+pub(crate) fn highlight_lines(
+	code: &str,
+	token: &str,
+	c: &CofgMarkdownHighlight,
+) -> Option<(Option<String>, Vec<String>)> {
+	let ss = syntaxes();
+	let syntax = syntax_for(token, code)?;
+	let mut lines = Vec::new();
+	let mut style = None;
+	match c.mode {
+		HighlightMode::Class => {
+			let mut state = ParseState::new(syntax);
+			let mut stack = ScopeStack::new();
+			for line in LinesWithEndings::from(code) {
+				let ops = state
+					.parse_line(line, ss)
+					.inspect_err(|e| log::warn!("highlight {token}: {e}"))
+					.ok()?;
+				let reopen: String = stack
+					.as_slice()
+					.iter()
+					.map(|scope| {
+						let classes: Vec<String> = scope
+							.build_string()
+							.split('.')
+							.map(|atom| format!("hl-{atom}"))
+							.collect();
+						format!(r#"<span class="{}">"#, classes.join(" "))
+					})
+					.collect();
+				let depth = stack.len() as isize;
+				let (html, delta) = syntect::html::line_tokens_to_classed_spans(
+					line,
+					&ops,
+					CLASS_STYLE,
+					&mut stack,
+				)
+				.inspect_err(|e| log::warn!("highlight {token}: {e}"))
+				.ok()?;
+				let open = (depth + delta).max(0) as usize;
+				lines.push(
+					format!("{reopen}{html}{}", "</span>".repeat(open)).replace(['\r', '\n'], ""),
+				);
+			}
+		}
+		HighlightMode::Inline => {
+			let Some(theme) = theme(&c.theme) else {
+				log::warn!("unknown highlight theme: {}", c.theme);
+				return None;
+			};
+			if let Some(bg) = theme.settings.background {
+				style = Some(format!(
+					"background-color:#{:02x}{:02x}{:02x}",
+					bg.r, bg.g, bg.b
+				));
+			}
+			let mut hl = syntect::easy::HighlightLines::new(syntax, theme);
+			for line in LinesWithEndings::from(code) {
+				let regions = hl
+					.highlight_line(line, ss)
+					.inspect_err(|e| log::warn!("highlight {token}: {e}"))
+					.ok()?;
+				let html = syntect::html::styled_line_to_highlighted_html(
+					&regions,
+					syntect::html::IncludeBackground::No,
+				)
+				.ok()?;
+				lines.push(html.replace(['\r', '\n'], ""));
+			}
+		}
+	}
+	Some((style, lines))
+}
+
+/// Transformer replacing fenced code blocks with highlighted HTML.
+pub(crate) struct Highlight<'a>(pub(crate) &'a CofgMarkdownHighlight);
+
+impl markdown_ppp::ast_transform::Transformer for Highlight<'_> {
+	fn transform_block(&mut self, block: Block) -> Block {
+		match block {
+			Block::CodeBlock(CodeBlock {
+				kind: CodeBlockKind::Fenced { info: Some(info) },
+				literal,
+			}) => match language(&info).and_then(|lang| highlight(&literal, lang, self.0)) {
+				Some(html) => Block::HtmlBlock(html),
+				None => Block::CodeBlock(CodeBlock {
+					kind: CodeBlockKind::Fenced { info: Some(info) },
+					literal,
+				}),
+			},
+			other => self.walk_transform_block(other),
+		}
+	}
+}
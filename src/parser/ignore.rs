@@ -0,0 +1,173 @@
+//! Ignore rules: `toc.ig` globs plus `.gitignore`-style files found in served directories
+//!
+//! WHY: `toc.ig` used to hide any path whose string merely contained a token (`node` also hid
+//! `nodes.md`). Patterns now follow gitignore syntax: `name` matches at any depth, a `/` anchors to
+//! the directory that defines it, a trailing `/` matches directories only and `!` re-includes.
+//! Each directory may add rules in the files named by `toc.ig_files` (`.gitignore`, `.tocignore`,
+//! ...); deeper files win over shallower ones, and `toc.ig` is the shallowest of all. As in git, a
+//! file inside an ignored directory cannot be re-included.
+//!
+//! Ignore files are parsed once and cached by mtime. The rules always hide paths from the TOC, nav,
+//! wiki links and directory views; `toc.ig_deny` additionally makes `main_req` answer 404.
+//!
+//! 中文：以 gitignore 語法處理 toc.ig 與各目錄的忽略檔（.gitignore、.tocignore、.myhttpignore）；
+//! toc.ig_deny 開啟時，被忽略的路徑也不提供存取。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::SystemTime;
+
+use log::{debug, warn};
+use wax::{Glob, Program as _};
+
+use crate::cofg::config::Cofg;
+
+/// One pattern line.
+#[derive(Debug)]
+struct Rule {
+	glob: Glob<'static>,
+	negate: bool,
+	dir_only: bool,
+}
+
+type Rules = Arc<Vec<Rule>>;
+
+static FILES: LazyLock<RwLock<HashMap<PathBuf, (SystemTime, Rules)>>> =
+	LazyLock::new(|| RwLock::new(HashMap::new()));
+/// Past this size, entries of removed files are dropped, then everything if still too many.
+const FILES_MAX: usize = 4096;
+
+/// Parse gitignore-syntax `lines`; patterns wax cannot compile are logged and skipped.
+fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Rule> {
+	let mut rules = Vec::new();
+	for line in lines {
+		let line = line.trim_end();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let (negate, pattern) = match line.strip_prefix('!') {
+			Some(rest) => (true, rest),
+			None => (false, line.strip_prefix('\\').unwrap_or(line)),
+		};
+		let (dir_only, pattern) = match pattern.strip_suffix('/') {
+			Some(rest) => (true, rest),
+			None => (false, pattern),
+		};
+		// A slash anywhere but the end anchors the pattern; otherwise it matches at any depth.
+		let pattern = match pattern.strip_prefix('/') {
+			Some(anchored) => anchored.to_string(),
+			None if pattern.contains('/') => pattern.to_string(),
+			None => format!("**/{pattern}"),
+		};
+		match Glob::new(&pattern) {
+			Ok(glob) => rules.push(Rule {
+				glob: glob.into_owned(),
+				negate,
+				dir_only,
+			}),
+			Err(e) => warn!("ignore pattern `{line}`: {e}"),
+		}
+	}
+	rules
+}
+
+/// Rules of the ignore file at `path`, cached until its mtime changes; `None` if there is none.
+fn load(path: &Path) -> Option<Rules> {
+	let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+	if let Ok(cache) = FILES.read()
+		&& let Some((cached_at, rules)) = cache.get(path)
+		&& *cached_at == modified
+	{
+		return Some(rules.clone());
+	}
+	let text = std::fs::read_to_string(path)
+		.inspect_err(|e| warn!("{}: {e}", path.display()))
+		.ok()?;
+	let rules: Rules = Arc::new(parse(text.lines()));
+	debug!("{}: {} rules", path.display(), rules.len());
+	if let Ok(mut cache) = FILES.write() {
+		if cache.len() >= FILES_MAX {
+			cache.retain(|path, _| path.exists());
+			if cache.len() >= FILES_MAX {
+				cache.clear();
+			}
+		}
+		cache.insert(path.to_path_buf(), (modified, rules.clone()));
+	}
+	Some(rules)
+}
+
+/// Ignore decisions under one root (normally `public_path`).
+///
+/// Keeps the rules of every directory it has visited, so a walk reads each ignore file once.
+pub(crate) struct Matcher<'c> {
+	root: PathBuf,
+	c: &'c Cofg,
+	base: Vec<Rule>,
+	/// Directory (relative to `root`) → rules of its own ignore files
+	dirs: HashMap<PathBuf, Vec<Rules>>,
+}
+
+impl<'c> Matcher<'c> {
+	pub(crate) fn new(root: &Path, c: &'c Cofg) -> Self {
+		Matcher {
+			root: root.to_path_buf(),
+			c,
+			base: parse(c.toc.ig.iter().map(String::as_str)),
+			dirs: HashMap::new(),
+		}
+	}
+
+	fn dir_rules(&mut self, dir: &Path) -> &[Rules] {
+		let (root, c) = (&self.root, self.c);
+		self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+			c.toc
+				.ig_files
+				.iter()
+				.filter_map(|name| load(&root.join(dir).join(name)))
+				.collect()
+		})
+	}
+
+	/// Last rule matching `rel` (already split at `dir`), `None` if no rule applies.
+	fn verdict(&mut self, comps: &[String], is_dir: bool) -> Option<bool> {
+		let matches = |rules: &[Rule], path: &str| {
+			rules
+				.iter()
+				.rev()
+				.find(|r| (is_dir || !r.dir_only) && r.glob.is_match(path))
+				.map(|r| !r.negate)
+		};
+		// Deepest ignore file first: the first one with a matching rule decides.
+		for depth in (0..comps.len()).rev() {
+			let dir: PathBuf = comps[..depth].iter().collect();
+			let path = comps[depth..].join("/");
+			for rules in self.dir_rules(&dir).iter().rev() {
+				if let Some(ignored) = matches(rules, &path) {
+					return Some(ignored);
+				}
+			}
+		}
+		matches(&self.base, &comps.join("/"))
+	}
+
+	/// Whether `path` (absolute or relative to the root) is ignored; a path outside the root never is.
+	pub(crate) fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+		let rel = if path.is_absolute() {
+			match path.strip_prefix(&self.root) {
+				Ok(rel) => rel,
+				Err(_) => return false,
+			}
+		} else {
+			path
+		};
+		let comps: Vec<String> = rel
+			.components()
+			.map(|c| c.as_os_str().to_string_lossy().into_owned())
+			.collect();
+		// An ignored ancestor hides everything below it.
+		(1..=comps.len())
+			.any(|n| self.verdict(&comps[..n], is_dir || n < comps.len()) == Some(true))
+	}
+}
@@ -0,0 +1,232 @@
+//! Images: `loading="lazy"`, intrinsic dimensions and downscaled `srcset` variants
+//!
+//! WHY: Screenshot-heavy pages load slowly on constrained links. Lazy loading defers off-screen
+//! images; `width`/`height` read from local files (header only) reserve space so the page does not
+//! jump while they arrive; and with `markdown.images.variants` the browser can pick a smaller copy
+//! (`img.png?w=480`) generated on first request and cached on disk.
+//!
+//! Only widths listed in config are ever generated, so clients cannot make the server resize to
+//! arbitrary sizes.
+//!
+//! 中文：圖片延遲載入、寫入原始尺寸避免版面跳動，並可產生縮圖供 srcset 使用。
+
+use std::path::Path;
+#[cfg(feature = "images")]
+use std::path::PathBuf;
+
+use markdown_ppp::ast::{Document, Image, Inline};
+
+use crate::cofg::config::Cofg;
+
+/// `width`, `height` of the image at `path`, reading only its header.
+#[cfg(feature = "images")]
+pub(crate) fn dimensions(path: &Path) -> Option<(u32, u32)> {
+	image::ImageReader::open(path)
+		.ok()?
+		.with_guessed_format()
+		.ok()?
+		.into_dimensions()
+		.map_err(|e| log::debug!("image dimensions {}: {e}", path.display()))
+		.ok()
+}
+
+/// `url` with `w=<width>` appended to its query.
+#[cfg(feature = "images")]
+fn variant_url(url: &str, width: u32) -> String {
+	let (url, fragment) = match url.split_once('#') {
+		Some((url, fragment)) => (url, format!("#{fragment}")),
+		None => (url, String::new()),
+	};
+	let sep = if url.contains('?') { '&' } else { '?' };
+	format!("{url}{sep}w={width}{fragment}")
+}
+
+/// Animated GIFs would lose their animation when re-encoded.
+#[cfg(feature = "images")]
+fn resizable(path: &Path) -> bool {
+	!path
+		.extension()
+		.and_then(|e| e.to_str())
+		.is_some_and(|e| e.eq_ignore_ascii_case("gif"))
+}
+
+/// `<img>` for `image` with the attributes from `markdown.images`; `None` if none applies.
+#[cfg_attr(not(feature = "images"), allow(unused_variables))]
+fn img_tag(c: &Cofg, image: &Image, local: &super::links::LocalFiles) -> Option<String> {
+	let cfg = &c.markdown.images;
+	let mut attrs = String::new();
+	if cfg.lazy {
+		attrs.push_str(r#" loading="lazy""#);
+	}
+	#[cfg(feature = "images")]
+	if let Some(path) = local.resolve(&image.destination).filter(|_| cfg.dimensions)
+		&& let Some((width, height)) = dimensions(&path)
+	{
+		attrs.push_str(&format!(r#" width="{width}" height="{height}""#));
+		let smaller: Vec<u32> = cfg
+			.variants
+			.widths
+			.iter()
+			.copied()
+			.filter(|w| *w < width)
+			.collect();
+		if cfg.variants.enable && resizable(&path) && !smaller.is_empty() {
+			let mut srcset: Vec<String> = smaller
+				.iter()
+				.map(|w| format!("{} {w}w", variant_url(&image.destination, *w)))
+				.collect();
+			srcset.push(format!("{} {width}w", image.destination));
+			attrs.push_str(&format!(
+				r#" srcset="{}""#,
+				handlebars::html_escape(&srcset.join(", "))
+			));
+		}
+	}
+	if attrs.is_empty() {
+		return None;
+	}
+	let mut tag = format!(
+		r#"<img src="{}" alt="{}""#,
+		handlebars::html_escape(&image.destination),
+		handlebars::html_escape(&image.alt)
+	);
+	if let Some(title) = &image.title {
+		tag.push_str(&format!(r#" title="{}""#, handlebars::html_escape(title)));
+	}
+	tag.push_str(&attrs);
+	tag.push('>');
+	Some(tag)
+}
+
+/// Render images that get attributes from `markdown.images` as raw `<img>`; the others keep
+/// markdown_ppp's own output.
+pub(crate) fn render(doc: Document, c: &Cofg, source: Option<&Path>) -> Document {
+	let local = super::links::LocalFiles::new(c, source);
+	super::inline::expand_inlines(doc, |inline| match inline {
+		Inline::Image(image) => match img_tag(c, &image, &local) {
+			Some(tag) => Ok(vec![Inline::Html(tag)]),
+			None => Err(Inline::Image(image)),
+		},
+		other => Err(other),
+	})
+}
+
+/// Directory variants and thumbnails are cached in: `variants.cache_dir`, else the per-user cache
+/// directory (`$XDG_CACHE_HOME/my-http-server/images` on Linux).
+///
+/// WHY: Cached files are served as-is, so nobody else may be able to plant them. A fixed directory
+/// under the shared temp dir allowed exactly that; the directory is now created private (0700 on
+/// Unix) and refused if other users can access it.
+#[cfg(feature = "images")]
+fn cache_dir(c: &Cofg) -> crate::error::AppResult<PathBuf> {
+	use crate::error::AppError::OtherError;
+
+	let dir = match &c.markdown.images.variants.cache_dir {
+		Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+		_ => directories::ProjectDirs::from("", "", "my-http-server")
+			.map(|dirs| dirs.cache_dir().join("images"))
+			.ok_or_else(|| {
+				OtherError("no user cache directory; set markdown.images.variants.cache_dir".into())
+			})?,
+	};
+	let mut builder = std::fs::DirBuilder::new();
+	builder.recursive(true);
+	#[cfg(unix)]
+	std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+	builder.create(&dir)?;
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		// `symlink_metadata`: a symlink could point anywhere, including a directory we do not own.
+		let meta = std::fs::symlink_metadata(&dir)?;
+		if !meta.is_dir() || meta.permissions().mode() & 0o077 != 0 {
+			return Err(OtherError(format!(
+				"image cache {} must be a directory only its owner can access (0700)",
+				dir.display()
+			)));
+		}
+	}
+	Ok(dir)
+}
+
+/// Path of `src` downscaled to `width`, generating it on first use.
+///
+/// `None` when no variant applies (width not configured, or not smaller than the original); the
+/// caller then serves the original.
+#[cfg(feature = "images")]
+pub(crate) fn variant(
+	c: &Cofg,
+	src: &Path,
+	width: u32,
+) -> crate::error::AppResult<Option<PathBuf>> {
+	let cfg = &c.markdown.images.variants;
+	if !cfg.enable || !cfg.widths.contains(&width) || !resizable(src) {
+		return Ok(None);
+	}
+	if dimensions(src).is_none_or(|(w, _)| width >= w) {
+		return Ok(None);
+	}
+	cached(c, src, &width.to_string(), |img| {
+		img.resize(width, u32::MAX, image::imageops::FilterType::Lanczos3)
+	})
+	.map(Some)
+}
+
+/// Path of the gallery thumbnail of `src` (fits `size`×`size`), generating it on first use.
+///
+/// `None` when the image is already that small or cannot be decoded (eg. SVG); the caller then
+/// serves the original.
+#[cfg(feature = "images")]
+pub(crate) fn thumbnail(c: &Cofg, src: &Path) -> crate::error::AppResult<Option<PathBuf>> {
+	let size = c.directory.thumbnail_size;
+	if dimensions(src).is_none_or(|(w, h)| w <= size && h <= size) {
+		return Ok(None);
+	}
+	// `thumbnail` trades quality for speed; fine at gallery size.
+	cached(c, src, &format!("thumb{size}"), |img| {
+		img.thumbnail(size, size)
+	})
+	.map(Some)
+}
+
+/// Cached output of `op` applied to `src`, keyed on `key`.
+#[cfg(feature = "images")]
+fn cached(
+	c: &Cofg,
+	src: &Path,
+	key: &str,
+	op: impl FnOnce(image::DynamicImage) -> image::DynamicImage,
+) -> crate::error::AppResult<PathBuf> {
+	use crate::error::AppError::OtherError;
+	use std::hash::{Hash, Hasher};
+
+	// Keyed on source path + mtime so an edited image gets fresh variants.
+	let mut hasher = std::collections::hash_map::DefaultHasher::new();
+	src.hash(&mut hasher);
+	std::fs::metadata(src)?.modified()?.hash(&mut hasher);
+	let ext = src.extension().and_then(|e| e.to_str()).unwrap_or("png");
+	let dir = cache_dir(c)?;
+	let out = dir.join(format!("{:016x}-{key}.{ext}", hasher.finish()));
+	if out.is_file() {
+		return Ok(out);
+	}
+
+	let img = image::ImageReader::open(src)?
+		.with_guessed_format()?
+		.decode()
+		.map_err(|e| OtherError(format!("decode {}: {e}", src.display())))?;
+	let resized = op(img);
+	// Write then rename so concurrent requests never see a half-written file.
+	let tmp = out.with_extension(format!(
+		"{ext}.{}-{:?}.tmp",
+		std::process::id(),
+		std::thread::current().id()
+	));
+	let format = image::ImageFormat::from_path(src)
+		.map_err(|e| OtherError(format!("{}: {e}", src.display())))?;
+	resized
+		.save_with_format(&tmp, format)
+		.map_err(|e| OtherError(format!("encode {}: {e}", src.display())))?;
+	std::fs::rename(&tmp, &out)?;
+	Ok(out)
+}
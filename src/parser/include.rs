@@ -0,0 +1,141 @@
+//! Markdown transclusion: `<!-- include: ../shared/footer.md -->`
+//!
+//! WHY: Shared snippets (install steps, warnings) are maintained once and pulled into many pages.
+//! The directive is an HTML comment, so other renderers (GitHub, editors) simply hide it. A block
+//! consisting of exactly one directive is replaced by the included file's AST *before* the other
+//! transforms run, so included content gets emoji, math, alerts, anchors, ... like the page itself.
+//!
+//! Paths are relative to the including file (or to `public_path` when they start with `/`) and
+//! must stay inside `public_path`; with `toc.ig_deny` ignored files cannot be included either.
+//! Cycles and nesting beyond `markdown.include.max_depth` are refused; a refused or unreadable
+//! include logs a warning and leaves the (invisible) comment.
+//!
+//! 中文：以 `<!-- include: 路徑 -->` 引入其他 Markdown；限制於 public_path 內，並偵測循環與深度上限。
+
+use std::path::{Path, PathBuf};
+
+use markdown_ppp::ast::{Block, Document};
+
+use crate::cofg::config::Cofg;
+
+use super::math::MathSpan;
+
+/// Target path of an include directive, if `html` is exactly one `<!-- include: ... -->`.
+pub(crate) fn directive(html: &str) -> Option<&str> {
+	let target = html
+		.trim()
+		.strip_prefix("<!--")?
+		.strip_suffix("-->")?
+		.trim()
+		.strip_prefix("include:")?
+		.trim();
+	(!target.is_empty() && !target.contains("-->")).then_some(target)
+}
+
+struct Includer<'a> {
+	c: &'a Cofg,
+	/// Canonical files currently being expanded (outermost first)
+	stack: Vec<PathBuf>,
+	/// Include levels currently open
+	depth: usize,
+	/// Shared with the page so placeholders from included files stay unique
+	math: &'a mut Vec<MathSpan>,
+}
+
+impl Includer<'_> {
+	fn blocks(&mut self, blocks: Vec<Block>, base: &Path) -> Vec<Block> {
+		blocks
+			.into_iter()
+			.flat_map(|b| self.block(b, base))
+			.collect()
+	}
+
+	fn block(&mut self, block: Block, base: &Path) -> Vec<Block> {
+		match block {
+			Block::HtmlBlock(html) => match directive(&html) {
+				Some(target) => self.include(target, base).unwrap_or_else(|e| {
+					log::warn!("include {target}: {e}");
+					vec![Block::HtmlBlock(html.clone())]
+				}),
+				None => vec![Block::HtmlBlock(html)],
+			},
+			Block::BlockQuote(blocks) => vec![Block::BlockQuote(self.blocks(blocks, base))],
+			Block::GitHubAlert(mut alert) => {
+				alert.blocks = self.blocks(alert.blocks, base);
+				vec![Block::GitHubAlert(alert)]
+			}
+			Block::List(mut list) => {
+				for item in &mut list.items {
+					item.blocks = self.blocks(std::mem::take(&mut item.blocks), base);
+				}
+				vec![Block::List(list)]
+			}
+			Block::FootnoteDefinition(mut def) => {
+				def.blocks = self.blocks(def.blocks, base);
+				vec![Block::FootnoteDefinition(def)]
+			}
+			other => vec![other],
+		}
+	}
+
+	fn include(&mut self, target: &str, base: &Path) -> crate::error::AppResult<Vec<Block>> {
+		use crate::error::AppError::OtherError;
+		if self.depth >= self.c.markdown.include.max_depth {
+			return Err(OtherError(format!(
+				"nesting deeper than {}",
+				self.c.markdown.include.max_depth
+			)));
+		}
+		let root = Path::new(&self.c.public_path).canonicalize()?;
+		let path = match target.strip_prefix('/') {
+			Some(abs) => root.join(abs),
+			None => base.join(target),
+		}
+		.canonicalize()?;
+		if !path.starts_with(&root) {
+			return Err(OtherError("outside public_path".into()));
+		}
+		if self.c.toc.ig_deny && super::ignore::Matcher::new(&root, self.c).is_ignored(&path, false)
+		{
+			return Err(OtherError("ignored (toc.ig_deny)".into()));
+		}
+		if self.stack.contains(&path) {
+			return Err(OtherError("include cycle".into()));
+		}
+		// An included file's front matter does not apply to the including page.
+		let (_, md) = super::front_matter::extract(std::fs::read_to_string(&path)?);
+		let doc = super::parse_source(md, self.c, self.math)?;
+		let dir = path.parent().unwrap_or(&root).to_path_buf();
+		self.stack.push(path);
+		self.depth += 1;
+		let blocks = self.blocks(doc.blocks, &dir);
+		self.depth -= 1;
+		self.stack.pop();
+		Ok(blocks)
+	}
+}
+
+/// Replace include directives in `doc` (the page at `source`, if known) with the included ASTs.
+///
+/// Without `source`, relative paths resolve against `public_path` (eg. generated TOC pages).
+pub(crate) fn expand(
+	doc: Document,
+	c: &Cofg,
+	source: Option<&Path>,
+	math: &mut Vec<MathSpan>,
+) -> Document {
+	let source = source.and_then(|p| p.canonicalize().ok());
+	let base = match source.as_deref().and_then(Path::parent) {
+		Some(dir) => dir.to_path_buf(),
+		None => PathBuf::from(&c.public_path),
+	};
+	let mut includer = Includer {
+		c,
+		stack: source.into_iter().collect(),
+		depth: 0,
+		math,
+	};
+	Document {
+		blocks: includer.blocks(doc.blocks, &base),
+	}
+}
@@ -0,0 +1,74 @@
+//! Inline-splitting AST helpers
+//!
+//! WHY: `Transformer::transform_inline` is 1→1, but features such as math or wiki links turn one
+//! inline into text + markup + text. markdown_ppp's expand API only recurses through blocks by
+//! default; this wrapper also descends into emphasis/links/table cells so every inline is visited
+//! exactly once.
+
+use markdown_ppp::ast::{Document, Inline};
+use markdown_ppp::ast_transform::{ExpandWith as _, Transformer};
+
+struct InlineExpander<F>(F);
+
+impl<F: FnMut(Inline) -> Result<Vec<Inline>, Inline>> Transformer for InlineExpander<F> {
+	fn expand_block(&mut self, block: markdown_ppp::ast::Block) -> Vec<markdown_ppp::ast::Block> {
+		self.walk_expand_block(block)
+	}
+
+	fn expand_inline(&mut self, inline: Inline) -> Vec<Inline> {
+		self.walk_expand_inline(inline)
+	}
+
+	fn walk_expand_inline(&mut self, inline: Inline) -> Vec<Inline> {
+		let inline = match (self.0)(inline) {
+			Ok(replaced) => return replaced,
+			Err(inline) => inline,
+		};
+		let mut expand = |children: Vec<Inline>| -> Vec<Inline> {
+			children
+				.into_iter()
+				.flat_map(|i| self.walk_expand_inline(i))
+				.collect()
+		};
+		match inline {
+			Inline::Emphasis(c) => vec![Inline::Emphasis(expand(c))],
+			Inline::Strong(c) => vec![Inline::Strong(expand(c))],
+			Inline::Strikethrough(c) => vec![Inline::Strikethrough(expand(c))],
+			Inline::Link(mut link) => {
+				link.children = expand(link.children);
+				vec![Inline::Link(link)]
+			}
+			Inline::LinkReference(mut link) => {
+				link.text = expand(link.text);
+				vec![Inline::LinkReference(link)]
+			}
+			other => vec![other],
+		}
+	}
+}
+
+/// Offer every inline in `doc` (outermost first) to `f`.
+///
+/// `f` returns `Ok(replacement)` to splice in new inlines (children are not visited), or hands the
+/// inline back via `Err` to keep it and descend into its children.
+pub(crate) fn expand_inlines(
+	doc: Document,
+	f: impl FnMut(Inline) -> Result<Vec<Inline>, Inline>,
+) -> Document {
+	let mut expander = InlineExpander(f);
+	// walk_expand_document always yields exactly one document
+	doc.expand_with(&mut expander)
+		.into_iter()
+		.next()
+		.unwrap_or(Document { blocks: Vec::new() })
+}
+
+/// Replace every `Inline::Text` in `doc` with the inlines returned by `split`.
+///
+/// `split` should return `vec![Inline::Text(text)]` for text it does not handle.
+pub(crate) fn expand_text(doc: Document, mut split: impl FnMut(String) -> Vec<Inline>) -> Document {
+	expand_inlines(doc, |inline| match inline {
+		Inline::Text(text) => Ok(split(text)),
+		other => Err(other),
+	})
+}
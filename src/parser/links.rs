@@ -0,0 +1,236 @@
+//! Link pass: prefix rewriting, external link attributes, broken `.md` links
+//!
+//! WHY: Imported READMEs link to their upstream host (`https://github.com/org/repo/blob/main/x.md`);
+//! `markdown.links.rewrite` maps such prefixes back to local paths so the pages stay navigable
+//! here. With `check_md`, links to local `.md` files (inline and reference-style) are checked
+//! against `public_path` (relative to the current page) and broken ones get [`BROKEN_CLASS`].
+//! External links can open in a new tab and carry a class for an icon. markdown_ppp's `<a>` has no
+//! class/target, so links that need attributes are re-emitted as raw open/close tags around their
+//! children; all others keep the printer's own output.
+//!
+//! 中文：連結處理：前綴改寫、外部連結新分頁與 class、檢查本地 .md 連結是否存在。
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use markdown_ppp::ast::{Block, Document, Inline, Link, LinkDefinition};
+use markdown_ppp::ast_transform::Transformer;
+
+use crate::cofg::config::{Cofg, CofgMarkdownLinks};
+
+/// CSS class on links to local `.md` files that do not exist.
+pub(crate) const BROKEN_CLASS: &str = "broken-link";
+
+/// `url` with the longest matching `rewrite` prefix replaced.
+pub(crate) fn rewrite(c: &CofgMarkdownLinks, url: &str) -> Option<String> {
+	let (prefix, to) = c
+		.rewrite
+		.as_ref()?
+		.iter()
+		.filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+		.max_by_key(|(prefix, _)| prefix.len())?;
+	Some(format!("{to}{}", &url[prefix.len()..]))
+}
+
+/// `http(s)://` or protocol-relative `//host`.
+pub(crate) fn is_external(url: &str) -> bool {
+	let lower = url.to_ascii_lowercase();
+	lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+/// Resolves local link targets against the current page.
+pub(crate) struct LocalFiles {
+	root: Option<PathBuf>,
+	/// Directory of the current page (relative links), `public_path` if unknown
+	base: PathBuf,
+}
+
+impl LocalFiles {
+	pub(crate) fn new(c: &Cofg, source: Option<&Path>) -> Self {
+		let root = Path::new(&c.public_path).canonicalize().ok();
+		let base = source
+			.and_then(Path::parent)
+			.map(Path::to_path_buf)
+			.or_else(|| root.clone())
+			.unwrap_or_default();
+		Self { root, base }
+	}
+
+	/// Percent-decoded path part of a local `url` (`None` for external/other schemes).
+	fn local_path(url: &str) -> Option<String> {
+		if is_external(url) || url.contains(':') {
+			return None;
+		}
+		let path = url.split(['#', '?']).next().unwrap_or_default();
+		Some(
+			percent_encoding::percent_decode_str(path)
+				.decode_utf8()
+				.ok()?
+				.into_owned(),
+		)
+	}
+
+	/// Path a local `url` points to, not yet checked (`None` for external/other schemes).
+	fn target(&self, url: &str) -> Option<PathBuf> {
+		let path = Self::local_path(url)?;
+		Some(match path.strip_prefix('/') {
+			Some(abs) => self.root.as_ref()?.join(abs),
+			None => self.base.join(&path),
+		})
+	}
+
+	/// Canonical path of the existing file `url` points to, if it is local and inside
+	/// `public_path` (outside is as good as missing: the server will not serve it).
+	pub(crate) fn resolve(&self, url: &str) -> Option<PathBuf> {
+		let root = self.root.as_ref()?;
+		self.target(url)?
+			.canonicalize()
+			.ok()
+			.filter(|p| p.starts_with(root) && p.is_file())
+	}
+
+	/// `Some(exists)` for links to local `.md` files, `None` for anything else.
+	pub(crate) fn md_exists(&self, url: &str) -> Option<bool> {
+		let path = Self::local_path(url)?;
+		if self.root.is_none() || !path.to_ascii_lowercase().ends_with(".md") {
+			return None;
+		}
+		Some(self.resolve(url).is_some())
+	}
+}
+
+/// Broken link targets already warned about; pages are rendered per request, so without this the
+/// log repeats the same warning on every view. Cleared when it grows past [`WARNED_MAX`].
+static WARNED: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(Default::default);
+const WARNED_MAX: usize = 4096;
+
+fn warn_broken(local: &LocalFiles, url: &str) {
+	let Some(target) = local.target(url) else {
+		return;
+	};
+	if let Ok(mut warned) = WARNED.lock() {
+		if warned.len() >= WARNED_MAX {
+			warned.clear();
+		}
+		if warned.insert(target) {
+			log::warn!("broken link: {url}");
+		}
+	}
+}
+
+/// `[label]: url` definitions by label, collected like markdown_ppp's printer does (later wins).
+fn definitions(blocks: &[Block], defs: &mut HashMap<Vec<Inline>, LinkDefinition>) {
+	for block in blocks {
+		match block {
+			Block::Definition(def) => {
+				defs.insert(def.label.clone(), def.clone());
+			}
+			Block::List(list) => {
+				for item in &list.items {
+					definitions(&item.blocks, defs);
+				}
+			}
+			Block::BlockQuote(blocks) => definitions(blocks, defs),
+			Block::GitHubAlert(alert) => definitions(&alert.blocks, defs),
+			_ => {}
+		}
+	}
+}
+
+fn open_tag(link: &Link, class: &[&str], new_tab: bool) -> String {
+	let mut tag = format!(
+		r#"<a href="{}""#,
+		handlebars::html_escape(&link.destination)
+	);
+	if let Some(title) = &link.title {
+		tag.push_str(&format!(r#" title="{}""#, handlebars::html_escape(title)));
+	}
+	if !class.is_empty() {
+		tag.push_str(&format!(r#" class="{}""#, class.join(" ")));
+	}
+	if new_tab {
+		tag.push_str(r#" target="_blank" rel="noopener noreferrer""#);
+	}
+	tag.push('>');
+	tag
+}
+
+/// Rewrites `[label]: url` definitions used by reference links.
+struct RewriteDefinitions<'a>(&'a CofgMarkdownLinks);
+
+impl Transformer for RewriteDefinitions<'_> {
+	fn transform_block(&mut self, block: Block) -> Block {
+		match block {
+			Block::Definition(mut def) => {
+				if let Some(url) = rewrite(self.0, &def.destination) {
+					def.destination = url;
+				}
+				Block::Definition(def)
+			}
+			other => self.walk_transform_block(other),
+		}
+	}
+}
+
+/// Apply `markdown.links` to every link in `doc` (the page at `source`, if known).
+pub(crate) fn render(doc: Document, c: &Cofg, source: Option<&Path>) -> Document {
+	let cfg = &c.markdown.links;
+	let doc = if cfg.rewrite.is_some() {
+		RewriteDefinitions(cfg).transform_document(doc)
+	} else {
+		doc
+	};
+	let local = LocalFiles::new(c, source);
+	let mut defs = HashMap::new();
+	definitions(&doc.blocks, &mut defs);
+	super::inline::expand_inlines(doc, |inline| {
+		let (link, original) = match inline {
+			Inline::Link(mut link) => {
+				if let Some(url) = rewrite(cfg, &link.destination) {
+					link.destination = url;
+				}
+				(link, None)
+			}
+			Inline::Autolink(url) => (
+				Link {
+					children: vec![Inline::Text(url.clone())],
+					destination: rewrite(cfg, &url).unwrap_or_else(|| url.clone()),
+					title: None,
+				},
+				None,
+			),
+			// Definitions were rewritten above; resolve the reference like the printer would.
+			Inline::LinkReference(reference) => match defs.get(&reference.label) {
+				Some(def) => (
+					Link {
+						children: reference.text.clone(),
+						destination: def.destination.clone(),
+						title: def.title.clone(),
+					},
+					Some(Inline::LinkReference(reference)),
+				),
+				None => return Err(Inline::LinkReference(reference)),
+			},
+			other => return Err(other),
+		};
+		let mut class = Vec::new();
+		let external = is_external(&link.destination);
+		if external && !cfg.external_class.is_empty() {
+			class.push(cfg.external_class.as_str());
+		}
+		if cfg.check_md && local.md_exists(&link.destination) == Some(false) {
+			warn_broken(&local, &link.destination);
+			class.push(BROKEN_CLASS);
+		}
+		let new_tab = external && cfg.new_tab;
+		if class.is_empty() && !new_tab {
+			// Nothing to add: keep markdown_ppp's own `<a>`.
+			return Err(original.unwrap_or(Inline::Link(link)));
+		}
+		let mut out = vec![Inline::Html(open_tag(&link, &class, new_tab))];
+		out.extend(link.children);
+		out.push(Inline::Html("</a>".to_string()));
+		Ok(out)
+	})
+}
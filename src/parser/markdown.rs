@@ -6,26 +6,188 @@
 //!
 //! 中文：將批次轉換/TOC 邏輯與線上請求分離，保持主流程簡潔；底線開頭為工具函式。
 
-use std::collections::BTreeMap;
-use std::path::Path;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 use log::{debug, trace};
+use serde::Serialize;
 use wax::Glob;
 use wax::walk::Entry as _;
 
 use crate::error::AppResult;
-use crate::{cofg::config::Cofg, error};
+use crate::{
+	cofg::config::{Cofg, CofgToc, TocSort},
+	error,
+};
 
-const NON_ALPHANUMERIC: &percent_encoding::AsciiSet =
+pub(crate) const NON_ALPHANUMERIC: &percent_encoding::AsciiSet =
 	&percent_encoding::NON_ALPHANUMERIC.remove(b'/');
 
 #[derive(Default, Debug)]
 struct TocNode {
 	children: BTreeMap<String, TocNode>,
+	/// Files only, filled when `toc.titles`/`toc.sort` need them (see [`doc_meta`])
+	meta: DocMeta,
+	modified: Option<SystemTime>,
 }
 
-fn emit_toc(node: &TocNode, prefix: &mut Vec<String>, out: &mut String, depth: usize) {
-	for (name, child) in &node.children {
+impl TocNode {
+	/// Newest modification time in this subtree.
+	fn newest(&self) -> Option<SystemTime> {
+		self.children
+			.values()
+			.filter_map(TocNode::newest)
+			.chain(self.modified)
+			.max()
+	}
+
+	/// Title to show instead of the file name (`toc.titles`).
+	fn title(&self, c: &Cofg) -> Option<&str> {
+		self.meta.title.as_deref().filter(|_| c.toc.titles)
+	}
+
+	/// Children in `toc.sort` order (ties keep name order).
+	fn sorted(&self, c: &Cofg) -> Vec<(&String, &TocNode)> {
+		let mut children: Vec<_> = self.children.iter().collect();
+		match c.toc.sort {
+			TocSort::Name => {}
+			TocSort::Title => children.sort_by_cached_key(|(name, node)| {
+				node.meta.title.as_deref().unwrap_or(name).to_lowercase()
+			}),
+			TocSort::Order => {
+				children.sort_by(|(_, a), (_, b)| match (a.meta.order, b.meta.order) {
+					(Some(a), Some(b)) => a.total_cmp(&b),
+					(Some(_), None) => Ordering::Less,
+					(None, Some(_)) => Ordering::Greater,
+					(None, None) => Ordering::Equal,
+				})
+			}
+			TocSort::Mtime => children.sort_by_key(|(_, node)| Reverse(node.newest())),
+		}
+		children
+	}
+}
+
+/// Titles are user text inside `[...]`; escape what would end or restyle the link.
+fn escape_label(title: &str) -> String {
+	let mut out = String::with_capacity(title.len());
+	for ch in title.chars() {
+		if "\\[]<>*_`".contains(ch) {
+			out.push('\\');
+		}
+		out.push(ch);
+	}
+	out
+}
+
+/// What the TOC needs from a markdown file's content.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct DocMeta {
+	/// Front matter `title`, else the first H1
+	pub(crate) title: Option<String>,
+	/// Front matter `order` (or `weight`)
+	pub(crate) order: Option<f64>,
+}
+
+/// First level-1 heading (ATX `# x` or setext `x\n===`) outside code fences.
+fn first_h1(md: &str) -> Option<String> {
+	let mut fence: Option<&str> = None;
+	let mut prev: Option<&str> = None;
+	for line in md.lines() {
+		let trimmed = line.trim_start();
+		if let Some(open) = fence {
+			if trimmed.starts_with(open) {
+				fence = None;
+			}
+			continue;
+		}
+		if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+			fence = Some(&trimmed[..3]);
+			prev = None;
+			continue;
+		}
+		if let Some(title) = trimmed.strip_prefix("# ") {
+			let title = title.trim().trim_end_matches('#').trim();
+			if !title.is_empty() {
+				return Some(title.to_string());
+			}
+		}
+		if let Some(prev) = prev
+			&& !trimmed.is_empty()
+			&& trimmed.trim_end().chars().all(|c| c == '=')
+		{
+			return Some(prev.trim().to_string());
+		}
+		prev = (!trimmed.is_empty()).then_some(line);
+	}
+	None
+}
+
+/// Title and order of the markdown source `md`.
+pub(crate) fn doc_meta_of(md: String) -> DocMeta {
+	let (front_matter, body) = super::front_matter::extract(md);
+	let title = front_matter
+		.get("title")
+		.and_then(|t| t.as_str())
+		.map(str::trim)
+		.filter(|t| !t.is_empty())
+		.map(String::from)
+		.or_else(|| first_h1(&body));
+	let order = ["order", "weight"].iter().find_map(|key| {
+		let value = front_matter.get(*key)?;
+		value
+			.as_f64()
+			.or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+	});
+	DocMeta { title, order }
+}
+
+// PERF: keyed by path + mtime so a TOC walk only re-reads files that changed.
+static DOC_META: LazyLock<RwLock<HashMap<PathBuf, (SystemTime, DocMeta)>>> =
+	LazyLock::new(Default::default);
+/// Past this size, entries of removed files are dropped, then everything if still too many.
+const DOC_META_MAX: usize = 16384;
+
+/// [`doc_meta_of`] for the file at `path`, cached until its mtime changes.
+fn doc_meta(path: &Path, modified: SystemTime) -> DocMeta {
+	if let Ok(cache) = DOC_META.read()
+		&& let Some((cached_at, meta)) = cache.get(path)
+		&& *cached_at == modified
+	{
+		return meta.clone();
+	}
+	let meta = match std::fs::read_to_string(path) {
+		Ok(md) => doc_meta_of(md),
+		Err(e) => {
+			debug!("toc meta {}: {e}", path.display());
+			DocMeta::default()
+		}
+	};
+	if let Ok(mut cache) = DOC_META.write() {
+		if cache.len() >= DOC_META_MAX {
+			cache.retain(|path, _| path.exists());
+			if cache.len() >= DOC_META_MAX {
+				cache.clear();
+			}
+		}
+		cache.insert(path.to_path_buf(), (modified, meta.clone()));
+	}
+	meta
+}
+
+/// Files rendered by the markdown renderer (`renderers`).
+pub(crate) fn is_markdown(path: &Path, c: &Cofg) -> bool {
+	path.extension()
+		.and_then(|e| e.to_str())
+		.and_then(|e| c.renderers.get(&e.to_ascii_lowercase()))
+		.is_some_and(|name| name == "markdown")
+}
+
+fn emit_toc(node: &TocNode, prefix: &mut Vec<String>, out: &mut String, depth: usize, c: &Cofg) {
+	for (name, child) in node.sorted(c) {
 		let path = if prefix.is_empty() {
 			name.clone()
 		} else {
@@ -35,36 +197,74 @@ fn emit_toc(node: &TocNode, prefix: &mut Vec<String>, out: &mut String, depth: u
 		trace!("emit_toc: node={node:?};prefix={prefix:#?};out={out};depth={depth}");
 		out.push_str(&format!(
 			"{indent}- [{}]({})\n",
-			name,
+			child.title(c).map_or_else(|| name.clone(), escape_label),
 			percent_encoding::utf8_percent_encode(&path.replace("\\", "/"), NON_ALPHANUMERIC)
 		));
 
+		if below_depth(depth + 1, c) {
+			prefix.push(name.clone());
+			emit_toc(child, prefix, out, depth + 1, c);
+			prefix.pop();
+		}
+	}
+}
+
+/// Whether entries at `depth` (0 = top level) are within `toc.depth`.
+fn below_depth(depth: usize, c: &Cofg) -> bool {
+	c.toc.depth == 0 || depth < c.toc.depth
+}
+
+/// [`emit_toc`] as nested HTML lists, each directory a closed `<details>` (`toc.collapse`).
+fn emit_toc_html(
+	node: &TocNode,
+	prefix: &mut Vec<String>,
+	out: &mut String,
+	depth: usize,
+	c: &Cofg,
+) {
+	out.push_str("<ul>");
+	for (name, child) in node.sorted(c) {
 		prefix.push(name.clone());
-		emit_toc(child, prefix, out, depth + 1);
+		let link = format!(
+			r#"<a href="{}">{}</a>"#,
+			percent_encoding::utf8_percent_encode(
+				&prefix.join("/").replace("\\", "/"),
+				NON_ALPHANUMERIC
+			),
+			handlebars::html_escape(child.title(c).unwrap_or(name))
+		);
+		if child.children.is_empty() || !below_depth(depth + 1, c) {
+			out.push_str(&format!("<li>{link}</li>"));
+		} else {
+			out.push_str(&format!("<li><details><summary>{link}</summary>"));
+			emit_toc_html(child, prefix, out, depth + 1, c);
+			out.push_str("</details></li>");
+		}
 		prefix.pop();
 	}
+	out.push_str("</ul>");
 }
 
-/// Generate an in-memory Markdown TOC listing files with configured extensions under `public_path`.
+/// Walk `root_path` for files with configured extensions into a sorted path-component tree.
 ///
-/// Each entry becomes `- [stem](percent-encoded-path)`; non-alphanumeric chars percent-encoded
-/// except '/'. Base directory is `toc.path`'s parent to allow placing TOC inside subfolder.
-///
-/// WHY: On-demand generation avoids stale TOC and eliminates pre-bake step. Lightweight glob walk
-/// acceptable since `/` root requests are comparatively infrequent.
-pub(crate) fn get_toc(root_path: &Path, c: &Cofg, title: Option<String>) -> AppResult<String> {
-	debug!("root:{}", root_path.display());
-	let public_path = &Path::new(&c.public_path).canonicalize()?;
-	let root_path = &root_path.canonicalize()?;
-
-	let mut toc_str = format!("# {}\n\n", title.unwrap_or("toc".to_string()));
+/// Shared by `get_toc` (Markdown listing) and `get_nav` (template sidebar) so both agree on which
+/// files exist and in what order.
+fn walk_toc(root_path: &Path, c: &Cofg) -> AppResult<TocNode> {
 	// Build a tree of path components for stable, de-duplicated recursive output
 	let mut root: TocNode = TocNode::default();
 
 	// 將 HashSet 轉為排好序的 Vec 並 join 成 glob 模式（修正原先 Vec::from(c.toc.ext) 編譯錯誤）
+	let read_meta = c.toc.titles || matches!(c.toc.sort, TocSort::Title | TocSort::Order);
 	let exts: Vec<String> = c.toc.ext.iter().cloned().collect();
 	let glob_pattern = format!("**/*.{{{}}}", exts.join(","));
 
+	// Ignore rules are relative to `public_path` (`root_path` may be a subdirectory).
+	let public_path = Path::new(&c.public_path)
+		.canonicalize()
+		.ok()
+		.filter(|p| root_path.starts_with(p));
+	let mut ignore = super::ignore::Matcher::new(public_path.as_deref().unwrap_or(root_path), c);
+
 	for entry in Glob::new(&glob_pattern)?.walk(root_path) {
 		let entry = entry?;
 		let path = entry
@@ -74,10 +274,8 @@ pub(crate) fn get_toc(root_path: &Path, c: &Cofg, title: Option<String>) -> AppR
 			.to_path_buf();
 		debug!("path: {}", path.display());
 
-		// Skip entries matching any ignore token
-		let path_str = path.to_string_lossy();
-		if c.toc.ig.iter().any(|ele| path_str.contains(ele)) {
-			debug!("continue");
+		if ignore.is_ignored(&root_path.join(&path), false) {
+			debug!("ignored");
 			continue;
 		}
 
@@ -89,32 +287,276 @@ pub(crate) fn get_toc(root_path: &Path, c: &Cofg, title: Option<String>) -> AppR
 		for part in comps {
 			cur = cur.children.entry(part).or_default();
 		}
+		if read_meta || c.toc.sort == TocSort::Mtime {
+			let abs = root_path.join(&path);
+			cur.modified = std::fs::metadata(&abs).and_then(|m| m.modified()).ok();
+			if read_meta
+				&& let Some(modified) = cur.modified
+				&& is_markdown(&abs, c)
+			{
+				cur.meta = doc_meta(&abs, modified);
+			}
+		}
 	}
+	Ok(root)
+}
 
-	// Emit recursively for arbitrary depth
-	let mut prefix: Vec<String> = Vec::new();
-	prefix.push(
-		root_path
-			.strip_prefix(public_path)?
-			.to_string_lossy()
-			.into_owned(),
-	);
-	emit_toc(&root, &mut prefix, &mut toc_str, 0);
+/// Files under `public_path` (relative, sorted) that `get_toc` would list, from the cached
+/// [`nav_tree`] walk.
+pub(crate) fn site_files(c: &Cofg) -> AppResult<Vec<PathBuf>> {
+	fn collect(node: &TocNode, prefix: &Path, out: &mut Vec<PathBuf>) {
+		for (name, child) in &node.children {
+			let path = prefix.join(name);
+			if child.children.is_empty() {
+				out.push(path);
+			} else {
+				collect(child, &path, out);
+			}
+		}
+	}
+	let mut out = Vec::new();
+	collect(&*nav_tree(c)?, Path::new(""), &mut out);
+	Ok(out)
+}
+
+/// Generate an in-memory Markdown TOC listing files with configured extensions under `root_path`.
+///
+/// Each entry becomes `- [name](percent-encoded-path)` (the document title with `toc.titles`),
+/// ordered by `toc.sort` and cut off below `toc.depth`. Links are relative to `public_path` (so
+/// `root_path` may be any directory inside it), non-alphanumeric chars percent-encoded except '/'.
+///
+/// WHY: On-demand generation avoids stale TOC and eliminates pre-bake step. Unlike the sidebar
+/// ([`get_nav`]), which every page needs, the TOC is only built for directory requests, so it is
+/// walked fresh each time.
+pub(crate) fn get_toc(root_path: &Path, c: &Cofg, title: Option<String>) -> AppResult<String> {
+	let mut toc_str = format!("# {}\n\n", title.unwrap_or("toc".to_string()));
+	let (root, mut prefix) = toc_root(root_path, c)?;
+	emit_toc(&root, &mut prefix, &mut toc_str, 0, c);
 	Ok(toc_str)
 }
 
-/// Parse raw markdown into AST using markdown_ppp with default config.
+/// The list of [`get_toc`] as collapsible HTML (`toc.collapse`), without the title.
+pub(crate) fn get_toc_html(root_path: &Path, c: &Cofg) -> AppResult<String> {
+	let (root, mut prefix) = toc_root(root_path, c)?;
+	let mut out = String::from(r#"<nav class="toc">"#);
+	emit_toc_html(&root, &mut prefix, &mut out, 0, c);
+	out.push_str("</nav>\n");
+	Ok(out)
+}
+
+/// Walked tree of `root_path` and the link prefix (`root_path` relative to `public_path`).
+fn toc_root(root_path: &Path, c: &Cofg) -> AppResult<(TocNode, Vec<String>)> {
+	debug!("root:{}", root_path.display());
+	let public_path = &Path::new(&c.public_path).canonicalize()?;
+	let root_path = &root_path.canonicalize()?;
+	let root = walk_toc(root_path, c)?;
+	let prefix = root_path
+		.strip_prefix(public_path)?
+		.to_string_lossy()
+		.into_owned();
+	Ok((root, vec![prefix]))
+}
+
+/// One entry of the `nav` template tree; directories carry `children`, files do not.
+#[derive(Debug, Serialize)]
+pub(crate) struct NavItem {
+	pub(crate) name: String,
+	/// Absolute, percent-encoded URL (`/dir/page%2Emd`)
+	pub(crate) url: String,
+	/// True for the current page and every directory containing it
+	pub(crate) active: bool,
+	pub(crate) children: Vec<NavItem>,
+}
+
+/// Link to a neighbouring page for `prev`/`next`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct NavLink {
+	pub(crate) name: String,
+	pub(crate) url: String,
+}
+
+/// Site navigation for one page: the whole tree plus pager links.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Nav {
+	pub(crate) nav: Vec<NavItem>,
+	pub(crate) prev: Option<NavLink>,
+	pub(crate) next: Option<NavLink>,
+}
+
+fn build_nav(
+	node: &TocNode,
+	prefix: &mut Vec<String>,
+	current: &[String],
+	pages: &mut Vec<NavLink>,
+	current_idx: &mut Option<usize>,
+	c: &Cofg,
+) -> Vec<NavItem> {
+	let mut items = Vec::with_capacity(node.children.len());
+	for (name, child) in node.sorted(c) {
+		let label = child.title(c).unwrap_or(name).to_string();
+		prefix.push(name.clone());
+		let url = format!(
+			"/{}",
+			percent_encoding::utf8_percent_encode(&prefix.join("/"), NON_ALPHANUMERIC)
+		);
+		let active = current.starts_with(prefix);
+		let children = build_nav(child, prefix, current, pages, current_idx, c);
+		if child.children.is_empty() {
+			if prefix.as_slice() == current {
+				*current_idx = Some(pages.len());
+			}
+			pages.push(NavLink {
+				name: label.clone(),
+				url: url.clone(),
+			});
+		}
+		prefix.pop();
+		items.push(NavItem {
+			name: label,
+			url,
+			active,
+			children,
+		});
+	}
+	items
+}
+
+/// How long [`nav_tree`] reuses a walk; new or renamed pages show up in the sidebar after this.
+const NAV_TTL: Duration = Duration::from_secs(5);
+
+/// Last [`walk_toc`] of `public_path` for [`get_nav`], with the settings it was walked with.
+struct NavTree {
+	walked_at: Instant,
+	public_path: PathBuf,
+	toc: CofgToc,
+	renderers: HashMap<String, String>,
+	root: Arc<TocNode>,
+}
+
+static NAV_TREE: LazyLock<RwLock<Option<NavTree>>> = LazyLock::new(Default::default);
+
+/// [`walk_toc`] over all of `public_path`, reused for [`NAV_TTL`].
+///
+/// PERF: Every rendered page needs the whole tree; walking `public_path` per request made large
+/// sites slow. A short TTL keeps the sidebar close to the tree without watching the filesystem.
+fn nav_tree(c: &Cofg) -> AppResult<Arc<TocNode>> {
+	let public_path = Path::new(&c.public_path).canonicalize()?;
+	if let Ok(cache) = NAV_TREE.read()
+		&& let Some(tree) = cache.as_ref()
+		&& tree.walked_at.elapsed() < NAV_TTL
+		&& tree.public_path == public_path
+		&& tree.toc == c.toc
+		&& tree.renderers == c.renderers
+	{
+		return Ok(tree.root.clone());
+	}
+	let root = Arc::new(walk_toc(&public_path, c)?);
+	if let Ok(mut cache) = NAV_TREE.write() {
+		*cache = Some(NavTree {
+			walked_at: Instant::now(),
+			public_path,
+			toc: c.toc.clone(),
+			renderers: c.renderers.clone(),
+			root: root.clone(),
+		});
+	}
+	Ok(root)
+}
+
+/// Build the `nav` tree over all of `public_path` and the `prev`/`next` pages around `current`.
+///
+/// `current` is relative to `public_path`. Page order is the depth-first order `get_toc` emits, so
+/// the pager walks the site the same way the TOC lists it. `prev`/`next` are `None` at the ends or
+/// when `current` is not part of the tree (eg. ignored by `toc.ig`).
+///
+/// WHY: Lets templates render a docs-style sidebar and pager without a hand-written index.
+pub(crate) fn get_nav(c: &Cofg, current: &Path) -> AppResult<Nav> {
+	let root = nav_tree(c)?;
+	let current: Vec<String> = current
+		.components()
+		.map(|c| c.as_os_str().to_string_lossy().to_string())
+		.collect();
+
+	let mut pages = Vec::new();
+	let mut current_idx = None;
+	let nav = build_nav(
+		&root,
+		&mut Vec::new(),
+		&current,
+		&mut pages,
+		&mut current_idx,
+		c,
+	);
+	let (prev, next) = match current_idx {
+		Some(i) => (
+			i.checked_sub(1).and_then(|i| pages.get(i)).cloned(),
+			pages.get(i + 1).cloned(),
+		),
+		None => (None, None),
+	};
+	Ok(Nav { nav, prev, next })
+}
+
+/// Parse raw markdown into AST with an explicit parser config (see [`parser_config`]).
 ///
-/// WHY: Encapsulate parser selection & config; caller obtains structured AST for potential future
-/// analysis (currently only rendered directly). Keeps `md2html` simpler.
+/// WHY: Encapsulate parser selection & config; callers go through [`super::parse_source`].
 /// 中文：抽離解析步驟，未來若需 AST 進階處理可在此擴充。
-pub(crate) fn parser_md(input: String) -> error::AppResult<markdown_ppp::ast::Document> {
+pub(crate) fn parser_md_with(
+	input: String,
+	config: markdown_ppp::parser::config::MarkdownParserConfig,
+) -> error::AppResult<markdown_ppp::ast::Document> {
 	use markdown_ppp::parser::parse_markdown;
-	// 內部需要 clone config 給 parser，但外部呼叫時可傳參考，避免重複 clone
 	Ok(parse_markdown(
-		markdown_ppp::parser::MarkdownParserState::with_config(
-			markdown_ppp::parser::config::MarkdownParserConfig::default(),
-		),
+		markdown_ppp::parser::MarkdownParserState::with_config(config),
 		&input,
 	)?)
 }
+
+/// Map `markdown.parser` onto markdown_ppp's config; a disabled construct is `Ignore`d, so the
+/// parser falls through to the next candidate (usually plain text).
+pub(crate) fn parser_config(
+	c: &crate::cofg::config::CofgMarkdownParser,
+) -> markdown_ppp::parser::config::MarkdownParserConfig {
+	use markdown_ppp::parser::config::{ElementBehavior, MarkdownParserConfig};
+	fn b<T>(enable: bool) -> ElementBehavior<T> {
+		if enable {
+			ElementBehavior::Parse
+		} else {
+			ElementBehavior::Ignore
+		}
+	}
+	let mut config = MarkdownParserConfig::default()
+		.with_block_blockquote_behavior(b(c.blockquote))
+		.with_block_github_alert_behavior(b(c.github_alert))
+		.with_block_heading_v1_behavior(b(c.heading_atx))
+		.with_block_heading_v2_behavior(b(c.heading_setext))
+		.with_block_thematic_break_behavior(b(c.thematic_break))
+		.with_block_list_behavior(b(c.list))
+		.with_block_code_block_behavior(b(c.code_block))
+		.with_block_html_block_behavior(b(c.html_block))
+		.with_block_footnote_definition_behavior(b(c.footnote_definition))
+		.with_block_link_definition_behavior(b(c.link_definition))
+		.with_block_table_behavior(b(c.table))
+		.with_inline_autolink_behavior(b(c.autolink))
+		.with_inline_link_behavior(b(c.link))
+		.with_inline_footnote_reference_behavior(b(c.footnote_reference))
+		.with_inline_reference_link_behavior(b(c.reference_link))
+		.with_inline_hard_newline_behavior(b(c.hard_newline))
+		.with_inline_image_behavior(b(c.image))
+		.with_inline_code_span_behavior(b(c.code_span))
+		.with_inline_emphasis_behavior(b(c.emphasis))
+		.with_inline_strikethrough_behavior(b(c.strikethrough));
+	if c.allow_no_space_in_headings {
+		config = config.with_allow_no_space_in_headings();
+	}
+	config
+}
+
+/// Map `markdown.printer` onto markdown_ppp's HTML printer config.
+pub(crate) fn printer_config(
+	c: &crate::cofg::config::CofgMarkdownPrinter,
+) -> markdown_ppp::html_printer::config::Config {
+	markdown_ppp::html_printer::config::Config::default()
+		.with_width(c.width)
+		.with_anchor_prefix(c.anchor_prefix.clone())
+}
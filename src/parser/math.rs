@@ -0,0 +1,245 @@
+//! LaTeX math (`$...$`, `$$...$$`) → MathML
+//!
+//! WHY: Math must be cut out of the source *before* markdown parsing; otherwise backslash escapes
+//! (`\{`, `\\`) and emphasis (`a*b*c`) mangle the TeX. `extract_into` swaps each span for an
+//! opaque placeholder, `render` turns placeholders back into MathML (or the original source when
+//! the TeX does not parse) after the AST is built. Conversion is fully server-side; no KaTeX/CDN needed.
+//!
+//! 中文：解析前先擷取數學式並以佔位符取代，解析後再轉為 MathML；失敗時保留原始文字。
+
+use latex2mathml::{DisplayStyle, latex_to_mathml};
+use markdown_ppp::ast::{Document, Inline};
+
+// Private-use code points: never produced by markdown_ppp and practically never typed by users.
+const OPEN: char = '\u{E000}';
+const CLOSE: char = '\u{E001}';
+
+/// One math span cut out of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MathSpan {
+	/// `$$...$$` (true) or `$...$` (false)
+	pub(crate) display: bool,
+	/// TeX between the delimiters
+	pub(crate) tex: String,
+	/// Original text including delimiters, used as fallback
+	pub(crate) source: String,
+}
+
+impl MathSpan {
+	/// MathML for this span, `None` if the TeX does not parse.
+	pub(crate) fn to_mathml(&self) -> Option<String> {
+		let style = if self.display {
+			DisplayStyle::Block
+		} else {
+			DisplayStyle::Inline
+		};
+		match latex_to_mathml(self.tex.trim(), style) {
+			// latex2mathml reports some errors in-band instead of via `Err`
+			Ok(mathml) if !mathml.contains("[PARSE ERROR") => Some(
+				// and emits `<`/`>` operators unescaped
+				mathml
+					.replace("<mo><</mo>", "<mo>&lt;</mo>")
+					.replace("<mo>></mo>", "<mo>&gt;</mo>"),
+			),
+			Ok(_) => {
+				log::debug!("math fallback: {}", self.source);
+				None
+			}
+			Err(e) => {
+				log::debug!("math fallback: {}: {e}", self.source);
+				None
+			}
+		}
+	}
+}
+
+fn placeholder(i: usize) -> String {
+	format!("{OPEN}{i}{CLOSE}")
+}
+
+/// If `line` opens a fenced code block, return its fence char and length.
+fn fence(line: &str) -> Option<(char, usize)> {
+	let trimmed = line.trim_start_matches(' ');
+	if line.len() - trimmed.len() > 3 {
+		return None;
+	}
+	let ch = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+	let len = trimmed.chars().take_while(|c| *c == ch).count();
+	(len >= 3).then_some((ch, len))
+}
+
+/// Index of the closing delimiter for math opened just before `from`, or `None`.
+///
+/// Inline (`$`): closing `$` must follow a non-space and not precede a digit (so `$5 and $10`
+/// stays text). Neither form may cross a blank line.
+fn find_close(src: &str, from: usize, display: bool) -> Option<usize> {
+	let bytes = src.as_bytes();
+	let mut i = from;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 2,
+			b'\n'
+				if src[i + 1..]
+					.trim_start_matches([' ', '\t'])
+					.starts_with('\n') =>
+			{
+				return None;
+			}
+			b'$' if display => {
+				if bytes.get(i + 1) == Some(&b'$') && i > from {
+					return Some(i);
+				}
+				i += 1;
+			}
+			b'$' => {
+				let before_digit = bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+				if i > from && !bytes[i - 1].is_ascii_whitespace() && !before_digit {
+					return Some(i);
+				}
+				i += 1;
+			}
+			_ => i += 1,
+		}
+	}
+	None
+}
+
+/// Replace math spans in `src` with placeholders (appended to `spans`), skipping fenced code and
+/// code spans. Included files append to the page's `spans`, so they share one placeholder space.
+///
+/// NOTE: indented code blocks are not recognised; fence code that contains `$`.
+pub(crate) fn extract_into(src: &str, spans: &mut Vec<MathSpan>) -> String {
+	let mut out = String::with_capacity(src.len());
+	let bytes = src.as_bytes();
+	let mut i = 0;
+	let mut line_start = true;
+
+	while i < bytes.len() {
+		if line_start && let Some((ch, len)) = fence(&src[i..]) {
+			// Copy the whole fenced block verbatim, up to and including the closing fence line.
+			let mut end = src[i..].find('\n').map_or(src.len(), |n| i + n + 1);
+			while end < src.len() {
+				let line_end = src[end..].find('\n').map_or(src.len(), |n| end + n + 1);
+				let closes = fence(&src[end..line_end]).is_some_and(|(c, l)| c == ch && l >= len);
+				end = line_end;
+				if closes {
+					break;
+				}
+			}
+			out.push_str(&src[i..end]);
+			i = end;
+			continue;
+		}
+		line_start = false;
+
+		match bytes[i] {
+			b'\n' => {
+				out.push('\n');
+				line_start = true;
+				i += 1;
+			}
+			b'\\' => {
+				let end = src[i + 1..]
+					.chars()
+					.next()
+					.map_or(i + 1, |c| i + 1 + c.len_utf8());
+				out.push_str(&src[i..end]);
+				i = end;
+			}
+			b'`' => {
+				// Code span: a backtick run closed by a run of the same length.
+				let run = src[i..].bytes().take_while(|b| *b == b'`').count();
+				let fence = &src[i..i + run];
+				let mut end = i + run;
+				let mut search = end;
+				while let Some(n) = src[search..].find(fence) {
+					let at = search + n;
+					let len = src[at..].bytes().take_while(|b| *b == b'`').count();
+					if len == run {
+						end = at + run;
+						break;
+					}
+					search = at + len;
+				}
+				out.push_str(&src[i..end]);
+				i = end;
+			}
+			b'$' => {
+				let display = bytes.get(i + 1) == Some(&b'$');
+				let open = if display { 2 } else { 1 };
+				let valid_open = display
+					|| bytes
+						.get(i + 1)
+						.is_some_and(|b| !b.is_ascii_whitespace() && *b != b'$');
+				match find_close(src, i + open, display).filter(|_| valid_open) {
+					Some(close) => {
+						let end = close + open;
+						out.push_str(&placeholder(spans.len()));
+						spans.push(MathSpan {
+							display,
+							tex: src[i + open..close].to_string(),
+							source: src[i..end].to_string(),
+						});
+						i = end;
+					}
+					None => {
+						out.push_str(&src[i..i + open]);
+						i += open;
+					}
+				}
+			}
+			_ => {
+				let ch_len = src[i..].chars().next().map_or(1, char::len_utf8);
+				out.push_str(&src[i..i + ch_len]);
+				i += ch_len;
+			}
+		}
+	}
+	out
+}
+
+/// Swap placeholders in text nodes for MathML (`Inline::Html`) or the original source.
+pub(crate) fn render(doc: Document, spans: &[MathSpan]) -> Document {
+	if spans.is_empty() {
+		return doc;
+	}
+	super::inline::expand_text(doc, |text| {
+		if !text.contains(OPEN) {
+			return vec![Inline::Text(text)];
+		}
+		let mut out = Vec::new();
+		let mut rest = text.as_str();
+		while let Some(start) = rest.find(OPEN) {
+			let after = &rest[start + OPEN.len_utf8()..];
+			let Some((idx, tail)) = after
+				.split_once(CLOSE)
+				.and_then(|(n, tail)| Some((n.parse::<usize>().ok()?, tail)))
+			else {
+				break;
+			};
+			let Some(span) = spans.get(idx) else { break };
+			if start > 0 {
+				out.push(Inline::Text(rest[..start].to_string()));
+			}
+			out.push(match span.to_mathml() {
+				Some(mathml) => Inline::Html(mathml),
+				None => Inline::Text(span.source.clone()),
+			});
+			rest = tail;
+		}
+		if !rest.is_empty() {
+			out.push(Inline::Text(rest.to_string()));
+		}
+		out
+	})
+}
+
+/// Restore placeholders that ended up outside text nodes (link targets, alt text, raw HTML).
+pub(crate) fn restore(html: String, spans: &[MathSpan]) -> String {
+	if !html.contains(OPEN) {
+		return html;
+	}
+	spans.iter().enumerate().fold(html, |html, (i, span)| {
+		html.replace(&placeholder(i), &handlebars::html_escape(&span.source))
+	})
+}
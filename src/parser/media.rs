@@ -0,0 +1,173 @@
+//! Media-aware directory views (gallery, players) and the `player` renderer
+//!
+//! WHY: A folder of screenshots or recordings renders as a bulleted list of file names through the
+//! TOC. Directory pages can instead show a thumbnail grid (`gallery`) or inline HTML5 players
+//! (`media`), chosen by [`super::directory::choose`]. Thumbnails are `img.png?thumb`, generated and cached
+//! like srcset variants (feature: images); without the feature the originals are scaled by CSS.
+//!
+//! Only direct children are shown, as in the `list` index (the `toc` view is the recursive one): a
+//! gallery of a whole tree would be unbounded.
+//!
+//! 中文：目錄可顯示為縮圖牆或影音播放器（?view= 或依比例自動），影音檔有獨立播放頁（?view=player）。
+
+use std::path::Path;
+
+use handlebars::html_escape;
+
+use crate::cofg::config::{Cofg, DirectoryView};
+use crate::error::AppResult;
+
+use super::{
+	PageContext,
+	directory::Listing,
+	renderer::{Rendered, Renderer},
+};
+
+const IMAGE: &[&str] = &[
+	"apng", "avif", "bmp", "gif", "jpeg", "jpg", "png", "svg", "webp",
+];
+const AUDIO: &[&str] = &["aac", "flac", "m4a", "mp3", "oga", "ogg", "opus", "wav"];
+const VIDEO: &[&str] = &["m4v", "mov", "mp4", "ogv", "webm"];
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum MediaKind {
+	Image,
+	Audio,
+	Video,
+}
+
+/// Media kind by (case-insensitive) extension.
+pub(crate) fn kind(path: &Path) -> Option<MediaKind> {
+	let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+	let ext = ext.as_str();
+	if IMAGE.contains(&ext) {
+		Some(MediaKind::Image)
+	} else if AUDIO.contains(&ext) {
+		Some(MediaKind::Audio)
+	} else if VIDEO.contains(&ext) {
+		Some(MediaKind::Video)
+	} else {
+		None
+	}
+}
+
+/// `<audio>` / `<video>` for `src`; `None` for other kinds.
+fn player(kind: MediaKind, src: &str) -> Option<String> {
+	let tag = match kind {
+		MediaKind::Audio => "audio",
+		MediaKind::Video => "video",
+		MediaKind::Image => return None,
+	};
+	Some(format!(
+		r#"<{tag} controls preload="metadata" src="{}"></{tag}>"#,
+		html_escape(src)
+	))
+}
+
+/// Full-page HTML5 player for one audio/video file (`?view=player`).
+pub(crate) struct PlayerRenderer;
+
+impl Renderer for PlayerRenderer {
+	fn render(&self, path: &Path, _c: &Cofg, _page: &PageContext) -> AppResult<Rendered> {
+		let name = path
+			.file_name()
+			.map(|n| n.to_string_lossy().into_owned())
+			.unwrap_or_default();
+		// `?raw` resolves against the page URL, so the element fetches this very file as bytes.
+		let media = kind(path)
+			.and_then(|kind| player(kind, "?raw"))
+			.unwrap_or_default();
+		Ok(Rendered {
+			body: format!(
+				r#"<figure class="player">{media}<figcaption>{} · <a href="?raw" download>download</a></figcaption></figure>"#,
+				html_escape(&name)
+			),
+			values: serde_json::Map::new(),
+		})
+	}
+}
+
+/// Gallery / media page for directory `rel` (relative to `public_path`); views other than
+/// `gallery` render as `media`.
+pub(crate) fn render_directory(
+	title: &str,
+	rel: &Path,
+	listing: &Listing,
+	view: DirectoryView,
+	c: &Cofg,
+) -> Rendered {
+	let url = |name: &str| super::directory::url(rel, name);
+	let link = |name: &str, label: &str| {
+		format!(
+			r#"<li><a href="{}">{}</a></li>"#,
+			html_escape(&url(name)),
+			html_escape(label)
+		)
+	};
+
+	let mut body = super::directory::header(title);
+	if !listing.dirs.is_empty() {
+		body.push_str("<ul class=\"directory-dirs\">");
+		for dir in &listing.dirs {
+			body.push_str(&link(dir, &format!("{dir}/")));
+		}
+		body.push_str("</ul>\n");
+	}
+
+	let mut rest = Vec::new();
+	match view {
+		DirectoryView::Gallery => {
+			let thumb = if cfg!(feature = "images") {
+				"?thumb"
+			} else {
+				""
+			};
+			body.push_str(&format!(
+				r#"<div class="gallery" style="--thumb: {}px">"#,
+				c.directory.thumbnail_size
+			));
+			for file in &listing.files {
+				if kind(Path::new(file)) != Some(MediaKind::Image) {
+					rest.push(file);
+					continue;
+				}
+				let src = html_escape(&url(file));
+				let name = html_escape(file);
+				body.push_str(&format!(
+					r#"<a class="gallery-item" href="{src}"><img src="{src}{thumb}" alt="{name}" loading="lazy"><span>{name}</span></a>"#
+				));
+			}
+			body.push_str("</div>\n");
+		}
+		_ => {
+			for file in &listing.files {
+				let Some(media) =
+					kind(Path::new(file)).and_then(|k| player(k, &format!("{}?raw", url(file))))
+				else {
+					rest.push(file);
+					continue;
+				};
+				body.push_str(&format!(
+					r#"<figure class="media-item">{media}<figcaption><a href="{}?view=player">{}</a></figcaption></figure>"#,
+					html_escape(&url(file)),
+					html_escape(file)
+				));
+				body.push('\n');
+			}
+		}
+	}
+	if !rest.is_empty() {
+		body.push_str("<ul class=\"directory-files\">");
+		for file in rest {
+			body.push_str(&link(file, file));
+		}
+		body.push_str("</ul>\n");
+	}
+
+	let mut values = serde_json::Map::new();
+	values.insert(
+		"directory".into(),
+		serde_json::json!({ "view": view, "dirs": listing.dirs.len(), "files": listing.files.len() }),
+	);
+	Rendered { body, values }
+}
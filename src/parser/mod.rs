@@ -9,10 +9,31 @@ use std::collections::HashMap;
 #[cfg(feature = "github_emojis")]
 use std::sync::OnceLock;
 
+use crate::cofg::config::HtmlPolicy;
 use crate::parser::templating::set_context_value;
+use handlebars::JsonValue;
 
+pub(crate) mod alert;
+pub(crate) mod directory;
+pub(crate) mod front_matter;
+pub(crate) mod heading;
+#[cfg(feature = "highlight")]
+pub(crate) mod highlight;
+pub(crate) mod ignore;
+pub(crate) mod images;
+pub(crate) mod include;
+pub(crate) mod inline;
+pub(crate) mod links;
 pub(crate) mod markdown;
+pub(crate) mod math;
+pub(crate) mod media;
+pub(crate) mod notebook;
+pub(crate) mod renderer;
+pub(crate) mod sanitize;
+pub(crate) mod source;
+pub(crate) mod table;
 pub(crate) mod templating;
+pub(crate) mod wiki;
 
 #[cfg(feature = "github_emojis")]
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -23,43 +44,83 @@ pub(crate) struct Emojis {
 #[cfg(feature = "github_emojis")]
 pub(crate) static EMOJIS: OnceLock<Emojis> = OnceLock::new();
 
-/// Convert a single markdown string into full HTML page via template `html-t` (file: configured via `hbs_path`).
+/// Structured per-page inputs for [`render_page`] that cannot be expressed as `name:value` pairs.
 ///
-/// Steps:
-/// 1. Acquire (or rebuild) template engine
-/// 2. Build fresh context (server + configured vars)
-/// 3. Apply extra template_data_list entries (e.g. `path:...`)
-/// 4. Parse markdown → AST → HTML body
-/// 5. Inject `body` then render compiled template
+/// WHY: `template_data_list` only carries scalars; request-derived data (file metadata,
+/// breadcrumbs, query parameters) needs arrays/objects. Kept as a struct so later per-page inputs
+/// can be added without touching every call site.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PageContext {
+	/// Merged into the template context after `template_data_list` (later wins).
+	pub(crate) values: serde_json::Map<String, JsonValue>,
+	/// File the markdown was read from; relative `include` directives resolve against it.
+	pub(crate) source: Option<std::path::PathBuf>,
+}
+
+/// Markdown source → AST, cutting math out first (appending to `math_spans`) when enabled.
+///
+/// Shared by the page itself and every file it includes.
+pub(crate) fn parse_source(
+	md: String,
+	c: &crate::cofg::config::Cofg,
+	math_spans: &mut Vec<math::MathSpan>,
+) -> crate::error::AppResult<markdown_ppp::ast::Document> {
+	// Math is cut out before parsing so markdown escapes/emphasis cannot touch the TeX.
+	let md = if c.markdown.math.enable {
+		math::extract_into(&md, math_spans)
+	} else {
+		md
+	};
+	markdown::parser_md_with(md, markdown::parser_config(&c.markdown.parser))
+}
+
+/// Wrap a renderer's output in the `html-t` template (file: configured via `hbs_path`).
+///
+/// Shared by every [`renderer::Renderer`] so all document types get the same context. A markdown
+/// page is [`markdown_body`] followed by this:
+/// 1. Split off front matter; its `markdown:` key overrides the config section for this page
+/// 2. Acquire (or rebuild) template engine
+/// 3. Build fresh context (server + configured vars)
+/// 4. Apply extra template_data_list entries (e.g. `path:...`)
+/// 5. Parse markdown → AST (includes, emoji, math, alerts, wiki links, links, images, highlight,
+///    heading anchors) → HTML body
+/// 6. Apply `markdown.sanitize.policy` to the fragment
+/// 7. Inject `body`/`outline` then render compiled template
+///
+/// Steps 1, 5 and 6 are [`markdown_body`]; 2–4 and 7 are this function.
 ///
 /// WHY: Keep side effects (engine caching, context assembly) localized while exposing a pure-ish
-/// interface to callers. Accepts owned `md` so upstream can cheaply `read_to_string` and transfer
-/// ownership without clone.
+/// interface to callers.
 ///
 /// Contract / 契約（重要行為與邊界）
 /// - Inputs:
-///   - `md`: 原始 Markdown（UTF-8）。不做串流；一次性解析。
 ///   - `c`: 讀取模板設定與 hot reload 旗標；實際模板引擎取得見 `templating::get_engine`。
 ///   - `template_data_list`: 以 `name:value`（含 `name:env:ENV`）格式注入 Context；
 ///     智慧型別推斷順序為 bool → i64 → string；無冒號或格式不正者將被忽略。
 ///     後加入的條目會覆寫同名既有值（包含由設定檔注入者）。
+///   - `page`: 請求相關的結構化鍵（如 `file`、`breadcrumbs`），於 `template_data_list` 之後合併。
+///   - `rendered`: 渲染器輸出的 HTML 片段與其附帶的鍵。
 /// - Output: 以邏輯名 `html-t` 渲染完成的完整 HTML 字串；Context 會包含：
-///   - 由 `get_context` 注入的內建鍵：`server-version`
-///   - 本函式注入的 `body`：Markdown 轉出的 HTML 片段
+///   - 由 `get_context` 注入的內建鍵：`server-version`、`version`（完整 `VERSION` 結構）
+///   - 由 `page`（`PageContext`）合併的結構化鍵
+///   - 本函式注入的 `body`：渲染器轉出的 HTML 片段
+///   - `markdown_body` 加入的 `front_matter`：頁首 YAML（無則為空物件）；其中 `markdown:` 覆寫本頁設定
+///   - `highlight_css`：啟用 class 模式程式碼高亮時的樣式表 URL（feature: highlight）
+///   - `markdown_body` 加入的 `outline`：標題樹 `[{level,text,id,children}]`（見 `heading::OutlineEntry`；未啟用 heading.anchors 時無 id）
 /// - Errors: 可能來自
-///   - Markdown 解析失敗（語法錯誤或非預期情形）
 ///   - 模板檔案註冊/解析失敗（檔案缺失或模板語法錯誤）
 ///   - 模板渲染失敗（缺鍵/型別不符等）→ 包裝為 `AppError::RenderError`
 /// - Side effects:
 ///   - 首次渲染若引擎尚未註冊 `html-t`，會以 `hbs_path` 進行註冊（讀檔）。
-///   - 以 `trace` 層級輸出 AST（大型文件可能產生大量日誌）。
 /// - Perf/Security notes:
 ///   - 正常模式引擎為快取重用；`hot_reload=true` 時每請求重建以反映模板改動。
 ///   - 渲染依賴本機模板檔案路徑；如內容根不可信，請配合上游路徑檢查避免 traversal。
-pub(crate) fn md2html(
-	md: String,
+///   - 原始 HTML 依 `markdown.sanitize.policy` 處理；預設 `allow`，內容不可信時請改用 `safe`。
+pub(crate) fn render_page(
 	c: &crate::cofg::config::Cofg,
 	template_data_list: Vec<String>,
+	page: PageContext,
+	rendered: renderer::Rendered,
 ) -> crate::error::AppResult<String> {
 	let mut engine = templating::get_engine(c)?;
 	let mut context = templating::get_context(c);
@@ -67,14 +128,69 @@ pub(crate) fn md2html(
 	for template_data in template_data_list {
 		set_context_value(&mut context, &template_data);
 	}
+	if let Some(data) = context.data_mut().as_object_mut() {
+		data.extend(page.values);
+		data.extend(rendered.values);
+	}
 	// Lazy 註冊模板：避免在未使用時就讀檔；同時配合 hot reload（引擎重建後將再次註冊）。
 	// 使用 resolve_hbs_path 以支持 XDG 配置目錄優先級
 	if !engine.has_template("html-t") {
 		let hbs_path = c.resolve_hbs_path();
 		engine.register_template_file("html-t", &hbs_path)?;
 	}
-	#[cfg_attr(not(feature = "github_emojis"), allow(unused_mut))]
-	let mut ast = markdown::parser_md(md)?;
+
+	// Contract: 模板預期取得 `body` 作為主要內容插槽。
+	context.data_mut()["body"] = JsonValue::String(rendered.body);
+	match engine.render_with_context("html-t", &context) {
+		Ok(o) => Ok(o),
+		Err(o) => {
+			log::error!("render_page:{}", o);
+			Err(crate::error::AppError::RenderError(o))
+		}
+	}
+}
+
+/// Markdown → `body` fragment plus the keys it adds (`front_matter`, `outline`, `highlight_css`);
+/// steps 1, 5 and 6 of [`render_page`]. Accepts owned `md` so callers can `read_to_string` and
+/// transfer ownership without a clone.
+///
+/// Fails when the markdown does not parse. Logs the AST at `trace` level (large documents log a
+/// lot).
+pub(crate) fn markdown_body(
+	md: String,
+	c: &crate::cofg::config::Cofg,
+	page: &PageContext,
+) -> crate::error::AppResult<renderer::Rendered> {
+	let (front_matter, md) = front_matter::extract(md);
+	// Per-page dialect: `markdown:` in front matter overrides the site section for this render.
+	let page_cofg;
+	let c = match front_matter.get("markdown") {
+		Some(overrides) => match front_matter::overlay(&c.markdown, overrides) {
+			Ok(markdown) => {
+				page_cofg = crate::cofg::config::Cofg {
+					markdown,
+					..c.clone()
+				};
+				&page_cofg
+			}
+			Err(e) => {
+				log::warn!("ignore front matter markdown overrides: {e}");
+				c
+			}
+		},
+		None => c,
+	};
+	let mut values = serde_json::Map::new();
+	values.insert("front_matter".into(), JsonValue::Object(front_matter));
+	let mut math_spans = Vec::new();
+	let mut ast = parse_source(md, c, &mut math_spans)?;
+	if c.markdown.include.enable {
+		ast = include::expand(ast, c, page.source.as_deref(), &mut math_spans);
+	}
+	// Before any stage adds trusted `Html` nodes of its own.
+	if c.markdown.sanitize.policy == HtmlPolicy::Escape {
+		ast = sanitize::escape_raw(ast);
+	}
 	// PERF: 只在 trace 開啟時輸出 AST；大型 Markdown 可能造成龐大日誌量。
 	log::trace!("ast={ast:#?}");
 	#[cfg(feature = "github_emojis")]
@@ -137,19 +253,41 @@ pub(crate) fn md2html(
 			},
 		)
 	}
+	ast = math::render(ast, &math_spans);
+	ast = alert::render(ast, &c.markdown.alerts);
+	if c.markdown.wiki_links.enable {
+		ast = wiki::render(ast, c);
+	}
+	ast = links::render(ast, c, page.source.as_deref());
+	ast = images::render(ast, c, page.source.as_deref());
+	#[cfg(feature = "highlight")]
+	if c.markdown.highlight.enable {
+		ast = markdown_ppp::ast_transform::Transform::transform_with(
+			ast,
+			highlight::Highlight(&c.markdown.highlight),
+		);
+		if c.markdown.highlight.mode == crate::cofg::config::HighlightMode::Class {
+			values.insert(
+				"highlight_css".into(),
+				JsonValue::String(highlight::CSS_PATH.into()),
+			);
+		}
+	}
+	let mut anchors = heading::HeadingAnchors::new(&c.markdown.heading);
+	ast = markdown_ppp::ast_transform::Transformer::transform_document(&mut anchors, ast);
+	values.insert(
+		"outline".into(),
+		serde_json::to_value(anchors.outline()).unwrap_or_default(),
+	);
 	log::trace!("ast={ast:#?}");
 	let html = markdown_ppp::html_printer::render_html(
 		&ast,
-		markdown_ppp::html_printer::config::Config::default(),
+		markdown::printer_config(&c.markdown.printer),
 	);
-
-	// Contract: 模板預期取得 `body` 作為主要內容插槽。
-	context.data_mut()["body"] = handlebars::JsonValue::String(html);
-	match engine.render_with_context("html-t", &context) {
-		Ok(o) => Ok(o),
-		Err(o) => {
-			log::error!("md2html:{}", o);
-			Err(crate::error::AppError::RenderError(o))
-		}
-	}
+	let html = math::restore(html, &math_spans);
+	let html = match c.markdown.sanitize.policy {
+		HtmlPolicy::Safe => sanitize::clean(&html),
+		HtmlPolicy::Allow | HtmlPolicy::Escape => html,
+	};
+	Ok(renderer::Rendered { body: html, values })
 }
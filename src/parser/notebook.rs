@@ -0,0 +1,275 @@
+//! Jupyter notebooks (`.ipynb`, nbformat 4)
+//!
+//! WHY: Notebooks are JSON; served as-is they are unreadable. The cells are stitched into one
+//! markdown document so markdown cells and code cells (as fenced blocks, highlighted when
+//! `markdown.highlight` is on) share a single outline, anchors and sanitize policy. Outputs are
+//! not markdown: each is swapped for a placeholder paragraph before the pipeline runs and spliced
+//! back into the finished fragment. Placeholders carry a random per-render nonce, so text in a
+//! cell can never be mistaken for one.
+//!
+//! Output HTML follows `markdown.sanitize.policy` like raw HTML in a markdown cell (`safe` cleans
+//! it, `escape` falls back to the `text/plain` representation). Images are embedded as `data:`
+//! URLs in `<img>`, which never runs scripts, so SVG needs no sanitizing. Cells and outputs of an
+//! unknown type are skipped with a warning rather than failing the page.
+//!
+//! 中文：將 .ipynb 轉為單一 Markdown 文件渲染，輸出（文字、HTML、PNG、SVG）以佔位符於渲染後插回。
+
+use std::{
+	collections::BTreeMap,
+	hash::{BuildHasher, RandomState},
+	path::Path,
+};
+
+use handlebars::html_escape;
+use log::warn;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+
+use crate::cofg::config::{Cofg, HtmlPolicy};
+use crate::error::{AppError::OtherError, AppResult};
+
+use super::{
+	PageContext,
+	renderer::{Rendered, Renderer},
+};
+
+// Private-use code points, distinct from the math placeholders.
+const OPEN: char = '\u{E002}';
+const CLOSE: char = '\u{E003}';
+
+/// `source` / `text` fields: a string or a list of lines.
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum Multiline {
+	One(String),
+	Many(Vec<String>),
+}
+
+impl Multiline {
+	fn join(&self) -> String {
+		match self {
+			Multiline::One(s) => s.clone(),
+			Multiline::Many(lines) => lines.concat(),
+		}
+	}
+}
+
+impl Default for Multiline {
+	fn default() -> Self {
+		Multiline::One(String::new())
+	}
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Notebook {
+	#[serde(default)]
+	metadata: serde_json::Value,
+	cells: Vec<Cell>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+enum Cell {
+	Markdown {
+		#[serde(default)]
+		source: Multiline,
+	},
+	Code {
+		#[serde(default)]
+		source: Multiline,
+		#[serde(default)]
+		outputs: Vec<Output>,
+	},
+	/// Targets other exporters (LaTeX, reST, ...); skipped like nbconvert's HTML export does.
+	Raw {},
+	/// Any other `cell_type` (eg. nbformat 3 `heading`); skipped with a warning.
+	#[serde(other)]
+	Unknown,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+	Stream {
+		#[serde(default)]
+		name: String,
+		#[serde(default)]
+		text: Multiline,
+	},
+	ExecuteResult {
+		#[serde(default)]
+		data: BTreeMap<String, Multiline>,
+	},
+	DisplayData {
+		#[serde(default)]
+		data: BTreeMap<String, Multiline>,
+	},
+	Error {
+		#[serde(default)]
+		ename: String,
+		#[serde(default)]
+		evalue: String,
+		#[serde(default)]
+		traceback: Vec<String>,
+	},
+	/// Any other `output_type`; skipped with a warning.
+	#[serde(other)]
+	Unknown,
+}
+
+/// `.ipynb` through [`super::markdown_body`].
+pub(crate) struct NotebookRenderer;
+
+impl Renderer for NotebookRenderer {
+	fn render(&self, path: &Path, c: &Cofg, page: &PageContext) -> AppResult<Rendered> {
+		render(&std::fs::read_to_string(path)?, c, page)
+	}
+}
+
+/// Placeholder for output `i`; `nonce` is drawn per render so cell text cannot forge one.
+fn placeholder(nonce: u64, i: usize) -> String {
+	format!("{OPEN}{nonce:016x}-{i}{CLOSE}")
+}
+
+/// Kernel language for code fences (`python` when the notebook does not say).
+fn language(metadata: &serde_json::Value) -> String {
+	metadata
+		.pointer("/language_info/name")
+		.or_else(|| metadata.pointer("/kernelspec/language"))
+		.and_then(|v| v.as_str())
+		.unwrap_or("python")
+		.to_string()
+}
+
+/// Fenced code block whose fence is longer than any backtick run in `code`.
+fn fenced(lang: &str, code: &str) -> String {
+	let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+	let fence = "`".repeat(longest.max(2) + 1);
+	format!("{fence}{lang}\n{}\n{fence}", code.trim_end_matches('\n'))
+}
+
+/// Drop ANSI escape sequences (colored tracebacks).
+fn strip_ansi(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(ch) = chars.next() {
+		if ch == '\u{1b}' {
+			if chars.next() == Some('[') {
+				// CSI: parameters until a final byte in `@`..=`~`
+				for ch in chars.by_ref() {
+					if ('@'..='~').contains(&ch) {
+						break;
+					}
+				}
+			}
+		} else {
+			out.push(ch);
+		}
+	}
+	out
+}
+
+fn pre(class: &str, text: &str) -> String {
+	format!(
+		"<pre class=\"nb-output {class}\">{}</pre>",
+		html_escape(text.trim_end_matches('\n'))
+	)
+}
+
+/// HTML for one output, `None` if it has nothing displayable.
+fn output_html(output: &Output, policy: HtmlPolicy) -> Option<String> {
+	let data = match output {
+		Output::Stream { name, text } => return Some(pre(&format!("nb-{name}"), &text.join())),
+		Output::Error {
+			ename,
+			evalue,
+			traceback,
+		} => {
+			let text = if traceback.is_empty() {
+				format!("{ename}: {evalue}")
+			} else {
+				strip_ansi(&traceback.join("\n"))
+			};
+			return Some(pre("nb-error", &text));
+		}
+		Output::ExecuteResult { data } | Output::DisplayData { data } => data,
+		Output::Unknown => {
+			warn!("ipynb: skipping output of unknown type");
+			return None;
+		}
+	};
+	if let Some(svg) = data.get("image/svg+xml") {
+		return Some(format!(
+			"<img class=\"nb-output\" alt=\"output\" src=\"data:image/svg+xml,{}\">",
+			utf8_percent_encode(&svg.join(), NON_ALPHANUMERIC)
+		));
+	}
+	for mime in ["image/png", "image/jpeg", "image/gif"] {
+		if let Some(b64) = data.get(mime) {
+			let b64: String = b64.join().split_whitespace().collect();
+			return Some(format!(
+				"<img class=\"nb-output\" alt=\"output\" src=\"data:{mime};base64,{}\">",
+				html_escape(&b64)
+			));
+		}
+	}
+	if let Some(html) = data.get("text/html") {
+		match policy {
+			HtmlPolicy::Allow => {
+				return Some(format!("<div class=\"nb-output\">{}</div>", html.join()));
+			}
+			HtmlPolicy::Safe => {
+				return Some(format!(
+					"<div class=\"nb-output\">{}</div>",
+					super::sanitize::clean(&html.join())
+				));
+			}
+			HtmlPolicy::Escape => {}
+		}
+	}
+	data.get("text/plain")
+		.map(|text| pre("nb-result", &text.join()))
+}
+
+/// Render notebook JSON `src` into the page fragment.
+pub(crate) fn render(src: &str, c: &Cofg, page: &PageContext) -> AppResult<Rendered> {
+	let notebook: Notebook =
+		serde_json::from_str(src).map_err(|e| OtherError(format!("ipynb: {e}")))?;
+	let lang = language(&notebook.metadata);
+	let policy = c.markdown.sanitize.policy;
+
+	let nonce = RandomState::new().hash_one(src);
+	let mut md = Vec::new();
+	let mut outputs = Vec::new();
+	for (i, cell) in notebook.cells.iter().enumerate() {
+		match cell {
+			Cell::Markdown { source } => md.push(source.join()),
+			Cell::Code {
+				source,
+				outputs: out,
+			} => {
+				md.push(fenced(&lang, &source.join()));
+				let html: String = out.iter().filter_map(|o| output_html(o, policy)).collect();
+				if !html.is_empty() {
+					md.push(placeholder(nonce, outputs.len()));
+					outputs.push(html);
+				}
+			}
+			Cell::Raw {} => {}
+			Cell::Unknown => warn!("ipynb: skipping cell {i} of unknown type"),
+		}
+	}
+
+	let mut rendered = super::markdown_body(md.join("\n\n"), c, page)?;
+	for (i, html) in outputs.iter().enumerate() {
+		let ph = placeholder(nonce, i);
+		rendered.body = rendered
+			.body
+			.replace(&format!("<p>{ph}</p>"), html)
+			.replace(&ph, html);
+	}
+	rendered.values.insert(
+		"notebook".into(),
+		serde_json::json!({ "language": lang, "cells": notebook.cells.len() }),
+	);
+	Ok(rendered)
+}
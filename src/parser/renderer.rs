@@ -0,0 +1,65 @@
+//! Renderer registry: which files are rendered into `html-t` instead of served as-is
+//!
+//! WHY: `main_req` used to special-case `.md`. Renderers are looked up by file extension through
+//! the `renderers` config map (`md: markdown`, `mdown: markdown`, ...), so another dialect only
+//! needs a config line and a new document type only needs a [`Renderer`] impl plus an entry in
+//! [`builtin`]. Every renderer produces a `body` fragment; [`super::render_page`] wraps it with the
+//! same template context (breadcrumbs, nav, file, query, ...) markdown pages get.
+//!
+//! 中文：依副檔名選擇渲染器（由設定 `renderers` 對應），輸出統一套用 html-t 模板。
+
+use std::path::Path;
+
+use handlebars::JsonValue;
+
+use crate::cofg::config::Cofg;
+use crate::error::AppResult;
+
+use super::PageContext;
+
+/// A renderer's output: the `body` fragment plus extra template keys (eg. `outline`).
+#[derive(Debug, Default)]
+pub(crate) struct Rendered {
+	pub(crate) body: String,
+	/// Merged after [`PageContext::values`] (later wins)
+	pub(crate) values: serde_json::Map<String, JsonValue>,
+}
+
+/// Turns one file under `public_path` into an HTML fragment.
+pub(crate) trait Renderer: Sync {
+	/// Render `path`; `page` carries the request-derived context (`query`, `source`, ...).
+	fn render(&self, path: &Path, c: &Cofg, page: &PageContext) -> AppResult<Rendered>;
+}
+
+/// Markdown through [`super::markdown_body`] (front matter, includes, transforms, sanitize).
+pub(crate) struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+	fn render(&self, path: &Path, c: &Cofg, page: &PageContext) -> AppResult<Rendered> {
+		super::markdown_body(std::fs::read_to_string(path)?, c, page)
+	}
+}
+
+/// Built-in renderer by config name.
+pub(crate) fn builtin(name: &str) -> Option<&'static dyn Renderer> {
+	match name {
+		"markdown" => Some(&MarkdownRenderer),
+		"csv" => Some(&super::table::TableRenderer { delimiter: b',' }),
+		"tsv" => Some(&super::table::TableRenderer { delimiter: b'\t' }),
+		"notebook" => Some(&super::notebook::NotebookRenderer),
+		"source" => Some(&super::source::SourceRenderer),
+		"player" => Some(&super::media::PlayerRenderer),
+		_ => None,
+	}
+}
+
+/// Renderer configured for `path`'s extension (case-insensitive), if any.
+pub(crate) fn for_path(path: &Path, c: &Cofg) -> Option<&'static dyn Renderer> {
+	let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+	let name = c.renderers.get(&ext)?;
+	let renderer = builtin(name);
+	if renderer.is_none() {
+		log::warn!("renderers.{ext}: unknown renderer `{name}`; serving as static file");
+	}
+	renderer
+}
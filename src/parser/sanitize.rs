@@ -0,0 +1,144 @@
+//! Raw HTML policy (`markdown.sanitize.policy`)
+//!
+//! WHY: markdown_ppp emits raw HTML from the source verbatim, so anyone who can write to
+//! `public_path` can inject scripts. Three policies:
+//! - `allow`: trust the content (previous behavior)
+//! - `safe`: clean the rendered fragment with an allow-list (ammonia). The list is ammonia's
+//!   default plus exactly the markup our own stages emit (anchors, alert octicons, MathML,
+//!   highlight classes/colors), so trusted output survives while `<script>`, `on*` handlers and
+//!   `javascript:` URLs do not.
+//! - `escape`: show every raw HTML node from the source as text
+//!
+//! 中文：依設定處理 Markdown 中的原始 HTML：全部允許、白名單過濾或全部跳脫。
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use markdown_ppp::ast::{Block, Document, Inline};
+use markdown_ppp::ast_transform::Transformer;
+
+/// Alert octicons.
+const SVG_TAGS: &[&str] = &["svg", "path"];
+
+/// Elements latex2mathml emits.
+const MATHML_TAGS: &[&str] = &[
+	"math",
+	"mi",
+	"mn",
+	"mo",
+	"ms",
+	"mtext",
+	"mspace",
+	"mrow",
+	"mfrac",
+	"msqrt",
+	"mroot",
+	"msub",
+	"msup",
+	"msubsup",
+	"munder",
+	"mover",
+	"munderover",
+	"mtable",
+	"mtr",
+	"mtd",
+	"mstyle",
+	"mpadded",
+	"mphantom",
+	"menclose",
+	"merror",
+	"semantics",
+	"annotation",
+];
+
+const GENERIC_ATTRIBUTES: &[&str] = &["class", "aria-hidden"];
+
+/// Elements that may keep an `id`: heading anchors (`markdown.heading`) and headings written
+/// with an explicit id. Anywhere else a document id could clobber ours (`#L10`, `#slug`).
+const ID_TAGS: &[&str] = &["a", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// `(tag, attributes)` added on top of ammonia's defaults.
+const TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+	("svg", &["viewBox", "version", "width", "height"]),
+	("path", &["d"]),
+	// external links (`markdown.links.new_tab`)
+	("a", &["target", "rel"]),
+	// `markdown.images`
+	("img", &["loading", "srcset"]),
+	// inline-mode highlighting
+	("span", &["style"]),
+	("pre", &["style"]),
+	("math", &["display", "xmlns"]),
+	(
+		"mo",
+		&["stretchy", "fence", "separator", "lspace", "rspace", "form"],
+	),
+	("mi", &["mathvariant"]),
+	("mstyle", &["displaystyle", "scriptlevel", "mathvariant"]),
+	("mspace", &["width"]),
+	("mtable", &["columnalign", "rowspacing", "columnspacing"]),
+	("mtd", &["columnalign"]),
+	("mover", &["accent"]),
+	("munder", &["accentunder"]),
+	("menclose", &["notation"]),
+	("annotation", &["encoding"]),
+];
+
+/// Style properties syntect's inline mode writes; everything else is dropped.
+const STYLE_PROPERTIES: &[&str] = &[
+	"color",
+	"background-color",
+	"font-weight",
+	"font-style",
+	"text-decoration",
+];
+
+static SAFE: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+	let mut builder = ammonia::Builder::default();
+	builder
+		.add_tags(SVG_TAGS)
+		.add_tags(MATHML_TAGS)
+		.add_generic_attributes(GENERIC_ATTRIBUTES)
+		.filter_style_properties(STYLE_PROPERTIES.iter().copied().collect::<HashSet<_>>())
+		// keep links byte-identical to the other policies; `rel` is a page-level concern
+		.link_rel(None);
+	for (tag, attributes) in TAG_ATTRIBUTES {
+		builder.add_tag_attributes(*tag, *attributes);
+	}
+	for tag in ID_TAGS {
+		builder.add_tag_attributes(*tag, ["id"]);
+	}
+	builder
+});
+
+/// Clean a rendered fragment with the `safe` allow-list.
+pub(crate) fn clean(html: &str) -> String {
+	SAFE.clean(html).to_string()
+}
+
+struct EscapeRaw;
+
+impl Transformer for EscapeRaw {
+	fn transform_block(&mut self, block: Block) -> Block {
+		match block {
+			Block::HtmlBlock(html) => {
+				Block::Paragraph(vec![Inline::Text(html.trim_end().to_string())])
+			}
+			other => self.walk_transform_block(other),
+		}
+	}
+
+	fn transform_inline(&mut self, inline: Inline) -> Inline {
+		match inline {
+			Inline::Html(html) => Inline::Text(html),
+			other => self.walk_transform_inline(other),
+		}
+	}
+}
+
+/// Turn every raw HTML node of the source into plain text.
+///
+/// Must run before stages that emit their own `Html` nodes (alerts, anchors, ...).
+pub(crate) fn escape_raw(doc: Document) -> Document {
+	EscapeRaw.transform_document(doc)
+}
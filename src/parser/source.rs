@@ -0,0 +1,136 @@
+//! Source viewer: text files as highlighted code with line numbers
+//!
+//! WHY: Config files and scripts linked from write-ups are downloaded or shown as bare text.
+//! `?view` (any text file) or a `renderers` entry (`yaml: source`) renders them in `html-t` with
+//! one anchor per line: `#L10` is a native fragment, `#L10-L20` ranges are marked by a small script
+//! in the template. Highlighting reuses `markdown.highlight` (feature: highlight); without it the
+//! lines are escaped plain text.
+//!
+//! Line numbers are drawn from `data-line` via CSS so copying code does not copy them. Only the
+//! first `source.max_lines` lines (and at most [`MAX_BYTES`]) are read: `?view` works on any file,
+//! including multi-GB media.
+//!
+//! 中文：原始碼檢視（行號與 #L10-L20 行錨點），?view 開啟，?raw 取得原始檔案。
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use handlebars::html_escape;
+
+use crate::cofg::config::Cofg;
+use crate::error::AppResult;
+
+use super::{
+	PageContext,
+	renderer::{Rendered, Renderer},
+};
+
+/// Any text file as numbered (and highlighted) lines.
+pub(crate) struct SourceRenderer;
+
+impl Renderer for SourceRenderer {
+	fn render(&self, path: &Path, c: &Cofg, _page: &PageContext) -> AppResult<Rendered> {
+		let file = std::fs::File::open(path)?;
+		// Extension, or the whole name for `Makefile`, `Dockerfile`, ...
+		let token = path
+			.extension()
+			.or_else(|| path.file_name())
+			.and_then(|t| t.to_str())
+			.unwrap_or_default();
+		render(file, token, c)
+	}
+}
+
+/// Upper bound on bytes read for one page, whatever `max_lines` allows (one huge line).
+pub(crate) const MAX_BYTES: u64 = 16 << 20;
+
+/// Binary files (NUL in the first 8 KiB, like git's heuristic) are not shown.
+pub(crate) fn is_text(bytes: &[u8]) -> bool {
+	!bytes[..bytes.len().min(8192)].contains(&0)
+}
+
+/// `<pre>` style and highlighted lines when `markdown.highlight` is on and the syntax is known.
+#[cfg(feature = "highlight")]
+fn highlight(
+	code: &str,
+	token: &str,
+	c: &Cofg,
+	values: &mut serde_json::Map<String, serde_json::Value>,
+) -> Option<(Option<String>, Vec<String>)> {
+	let cfg = &c.markdown.highlight;
+	if !cfg.enable {
+		return None;
+	}
+	let highlighted = super::highlight::highlight_lines(code, token, cfg)?;
+	if cfg.mode == crate::cofg::config::HighlightMode::Class {
+		values.insert("highlight_css".into(), super::highlight::CSS_PATH.into());
+	}
+	Some(highlighted)
+}
+
+#[cfg(not(feature = "highlight"))]
+fn highlight(
+	_code: &str,
+	_token: &str,
+	_c: &Cofg,
+	_values: &mut serde_json::Map<String, serde_json::Value>,
+) -> Option<(Option<String>, Vec<String>)> {
+	None
+}
+
+/// Render `input` as source; `token` (extension or file name) picks the syntax.
+pub(crate) fn render(input: impl Read, token: &str, c: &Cofg) -> AppResult<Rendered> {
+	let mut values = serde_json::Map::new();
+	let max_lines = c.source.max_lines.max(1);
+	let mut reader = BufReader::new(input.take(MAX_BYTES));
+	let mut bytes = Vec::new();
+	let mut count = 0;
+	while count < max_lines && reader.read_until(b'\n', &mut bytes)? > 0 {
+		count += 1;
+		if !is_text(&bytes) {
+			break;
+		}
+	}
+	if !is_text(&bytes) {
+		return Ok(Rendered {
+			body: r#"<p class="source-info">Binary file · <a href="?raw">raw</a></p>"#.into(),
+			values,
+		});
+	}
+	let truncated = !reader.fill_buf()?.is_empty();
+	let text = String::from_utf8_lossy(&bytes);
+
+	let highlighted = highlight(&text, token, c, &mut values);
+	let (pre_style, lines) = highlighted.unwrap_or_else(|| {
+		let plain = text.lines().map(|l| html_escape(l).to_string()).collect();
+		(None, plain)
+	});
+
+	let mut body = if truncated {
+		format!(
+			r#"<p class="source-info">first {} lines shown"#,
+			lines.len()
+		)
+	} else {
+		format!(r#"<p class="source-info">{} lines"#, lines.len())
+	};
+	body.push_str(r#" · <a href="?raw">raw</a></p>"#);
+	body.push_str("\n<pre class=\"hl-code source\"");
+	if let Some(style) = pre_style {
+		body.push_str(&format!(r#" style="{style}""#));
+	}
+	body.push_str("><code>");
+	for (i, line) in lines.iter().enumerate() {
+		let n = i + 1;
+		body.push_str(&format!(
+			r##"<span class="line" id="L{n}"><a class="ln" href="#L{n}" data-line="{n}"></a>{line}</span>"##
+		));
+	}
+	body.push_str("</code></pre>\n");
+
+	values.insert(
+		"source".into(),
+		serde_json::json!({ "shown": lines.len(), "truncated": truncated }),
+	);
+	Ok(Rendered { body, values })
+}
@@ -0,0 +1,158 @@
+//! CSV / TSV as HTML tables
+//!
+//! WHY: Reports published as `.csv` are downloaded by browsers instead of shown. The table
+//! renderer streams the file once, keeps only the requested page (`?page=N`, `table.page_size`
+//! rows) and counts the rest, so a multi-megabyte export renders in bounded memory. `?raw` (handled
+//! by `main_req` for every renderer) still serves the original bytes.
+//!
+//! Sorting is client-side and only reorders the current page (`table.sortable`, script in
+//! `html-t`).
+//!
+//! 中文：將 CSV/TSV 以分頁表格呈現，可點擊標題排序；?raw 取得原始檔案。
+
+use std::{io::Read, path::Path};
+
+use handlebars::html_escape;
+
+use crate::cofg::config::{Cofg, CofgTable, TableHeader};
+use crate::error::{AppError::OtherError, AppResult};
+
+use super::{
+	PageContext,
+	renderer::{Rendered, Renderer},
+};
+
+/// Delimited text file (`csv`: comma, `tsv`: tab).
+pub(crate) struct TableRenderer {
+	pub(crate) delimiter: u8,
+}
+
+impl Renderer for TableRenderer {
+	fn render(&self, path: &Path, c: &Cofg, page: &PageContext) -> AppResult<Rendered> {
+		let page_no = page
+			.values
+			.get("query")
+			.and_then(|q| q.get("page"))
+			.and_then(|p| p.as_str())
+			.and_then(|p| p.parse().ok())
+			.unwrap_or(1);
+		render(
+			std::fs::File::open(path)?,
+			self.delimiter,
+			&c.table,
+			page_no,
+		)
+	}
+}
+
+/// Auto header detection: every cell present, none numeric, no duplicates.
+fn looks_like_header(row: &[String]) -> bool {
+	!row.is_empty()
+		&& row
+			.iter()
+			.all(|cell| !cell.trim().is_empty() && cell.trim().parse::<f64>().is_err())
+		&& row
+			.iter()
+			.enumerate()
+			.all(|(i, cell)| !row[..i].contains(cell))
+}
+
+fn row_html(tag: &str, row: &[String]) -> String {
+	let cells: String = row
+		.iter()
+		.map(|cell| format!("<{tag}>{}</{tag}>", html_escape(cell)))
+		.collect();
+	format!("<tr>{cells}</tr>\n")
+}
+
+/// Render page `page_no` (1-based) of delimited `input`; a page past the end is empty and links back.
+pub(crate) fn render(
+	input: impl Read,
+	delimiter: u8,
+	cfg: &CofgTable,
+	page_no: usize,
+) -> AppResult<Rendered> {
+	let mut reader = csv::ReaderBuilder::new()
+		.delimiter(delimiter)
+		.has_headers(false)
+		.flexible(true)
+		.from_reader(input);
+	let page_size = cfg.page_size.max(1);
+	let page_no = page_no.max(1);
+	// `?page` is user input: a huge N must land past the end, not overflow.
+	let start = (page_no - 1).saturating_mul(page_size);
+
+	let mut header = None;
+	let mut rows = Vec::new();
+	let mut total = 0;
+	for (i, record) in reader.byte_records().enumerate() {
+		let record = record.map_err(|e| OtherError(format!("csv: {e}")))?;
+		let row: Vec<String> = record
+			.iter()
+			.map(|cell| String::from_utf8_lossy(cell).into_owned())
+			.collect();
+		let is_header = i == 0
+			&& match cfg.header {
+				TableHeader::Always => true,
+				TableHeader::Never => false,
+				TableHeader::Auto => looks_like_header(&row),
+			};
+		if is_header {
+			header = Some(row);
+			continue;
+		}
+		if (start..start.saturating_add(page_size)).contains(&total) {
+			rows.push(row);
+		}
+		total += 1;
+	}
+	let pages = total.div_ceil(page_size).max(1);
+
+	let class = if cfg.sortable && header.is_some() {
+		"csv-table sortable"
+	} else {
+		"csv-table"
+	};
+	let mut body = format!("<table class=\"{class}\">\n");
+	if let Some(header) = &header {
+		body.push_str(&format!("<thead>{}</thead>\n", row_html("th", header)));
+	}
+	body.push_str("<tbody>\n");
+	for row in &rows {
+		body.push_str(&row_html("td", row));
+	}
+	body.push_str("</tbody>\n</table>\n");
+
+	let shown = if rows.is_empty() {
+		format!("0 of {total} rows")
+	} else {
+		format!("rows {}–{} of {total}", start + 1, start + rows.len())
+	};
+	body.push_str("<nav class=\"table-pager\">");
+	if page_no > 1 {
+		body.push_str(&format!(
+			"<a rel=\"prev\" href=\"?page={}\">&larr;</a> ",
+			(page_no - 1).min(pages)
+		));
+	}
+	body.push_str(&format!("{shown} (page {page_no} / {pages})"));
+	if page_no < pages {
+		body.push_str(&format!(
+			" <a rel=\"next\" href=\"?page={}\">&rarr;</a>",
+			page_no + 1
+		));
+	}
+	body.push_str(" · <a href=\"?raw\">raw</a></nav>\n");
+
+	let mut values = serde_json::Map::new();
+	values.insert(
+		"table".into(),
+		serde_json::json!({
+			"rows": total,
+			"page": page_no,
+			"pages": pages,
+			"sortable": cfg.sortable && header.is_some(),
+		}),
+	);
+	Ok(Rendered { body, values })
+}
@@ -1,13 +1,15 @@
 //! Template engine helpers
 //!
 //! WHY: Isolate template engine initialization & context population so markdown → HTML pipeline
-//! stays pure/minimal (`md2html`). Engine instance is cached via `OnceCell<RwLock<_>>` for reuse
-//! (bytecode cache active) unless `hot_reload` signals we must reconstruct to pick up file edits.
+//! stays pure/minimal (`markdown_body` / `render_page`). Engine instance is cached via
+//! `OnceCell<RwLock<_>>` for reuse (bytecode cache active) unless `hot_reload` signals we must
+//! reconstruct to pick up file edits.
 
-use handlebars::{Context, Handlebars};
+use handlebars::{Context, Handlebars, JsonValue};
 
 use log::error;
 use serde_json::json;
+use std::path::Path;
 use std::sync::{OnceLock, RwLock};
 
 use crate::error::{AppError, AppResult};
@@ -60,14 +62,15 @@ pub(crate) fn set_context_value(context: &mut Context, data: &str) {
 
 /// Build a fresh template context with server metadata and configured variables.
 ///
-/// Always includes `server-version`. Then folds `templating.value` items through
-/// `set_context_value` to infer types.
+/// Always includes `server-version` and the full `version` struct. Then folds `templating.value`
+/// items through `set_context_value` to infer types.
 ///
 /// WHY: Decouple config parsing from render path; context creation is cheap and explicit instead
 /// of sharing mutable state across renders.
 pub(crate) fn get_context(c: &crate::cofg::config::Cofg) -> Context {
 	let mut context = Context::wraps(json!({
-	  "server-version": env!("CARGO_PKG_VERSION")
+	  "server-version": env!("CARGO_PKG_VERSION"),
+	  "version": crate::VERSION,
 	}))
 	.unwrap_or_else(|e| {
 		error!("Failed to create template context: {}", e);
@@ -81,6 +84,55 @@ pub(crate) fn get_context(c: &crate::cofg::config::Cofg) -> Context {
 
 	context
 }
+
+/// Describe a file on disk for the template `file` key: `name`, `size` (bytes), `modified`
+/// (Unix seconds) and `modified_http` (IMF-fixdate, e.g. for "last updated" footers).
+///
+/// WHY: Metadata failures must not break rendering; missing fields are simply omitted.
+pub(crate) fn file_context(path: &Path) -> JsonValue {
+	let mut file = serde_json::Map::new();
+	if let Some(name) = path.file_name() {
+		file.insert("name".into(), name.to_string_lossy().into());
+	}
+	if let Ok(metadata) = std::fs::metadata(path) {
+		file.insert("size".into(), metadata.len().into());
+		if let Ok(modified) = metadata.modified() {
+			if let Ok(d) = modified.duration_since(std::time::UNIX_EPOCH) {
+				file.insert("modified".into(), d.as_secs().into());
+			}
+			file.insert(
+				"modified_http".into(),
+				actix_web::http::header::HttpDate::from(modified)
+					.to_string()
+					.into(),
+			);
+		}
+	}
+	JsonValue::Object(file)
+}
+
+/// Build the `breadcrumbs` array for a path relative to `public_path`.
+///
+/// Always starts with the root crumb (`name: "root"`, `url: "/"`); each following crumb is one
+/// path segment whose `url` is percent-encoded the same way as TOC links.
+pub(crate) fn breadcrumbs(rel: &Path) -> JsonValue {
+	let mut crumbs = vec![json!({ "name": "root", "url": "/" })];
+	let mut url = String::new();
+	for comp in rel.components() {
+		let name = comp.as_os_str().to_string_lossy();
+		if name == "." {
+			continue;
+		}
+		url.push('/');
+		url.push_str(
+			&percent_encoding::utf8_percent_encode(&name, super::markdown::NON_ALPHANUMERIC)
+				.to_string(),
+		);
+		crumbs.push(json!({ "name": name, "url": url }));
+	}
+	JsonValue::Array(crumbs)
+}
+
 static ENGINE: OnceLock<RwLock<Handlebars>> = OnceLock::new();
 
 /// Retrieve (or rebuild under hot reload) the template engine.
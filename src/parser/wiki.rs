@@ -0,0 +1,154 @@
+//! Wiki-style links: `[[Page Name]]`, `[[Page Name|label]]`, `[[Page#Section]]`
+//!
+//! WHY: Notes imported from wiki tools link pages by name rather than path. markdown_ppp parses
+//! `[[x]]` as a (dangling) reference nested in a reference; we match that shape and rewrite it to
+//! a normal link. Targets resolve case-insensitively against markdown files (every extension
+//! `renderers` maps to `markdown`) under `public_path`, using the same walk/ignore rules as
+//! `get_toc`; a relative path (`dir/page`) wins over a bare name, and among equal names the
+//! shallowest file wins.
+//!
+//! 中文：支援 `[[頁面]]` 維基連結，依檔名於 public_path 下尋找對應 Markdown 檔；找不到時加上專用 class。
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use markdown_ppp::ast::{Document, Inline, Link, LinkReference};
+
+use crate::cofg::config::Cofg;
+
+/// CSS class on the `<span>` emitted for targets that do not resolve.
+pub(crate) const MISSING_CLASS: &str = "wikilink-missing";
+
+/// Lookup tables from lowercase target to URL.
+#[derive(Debug, Default)]
+pub(crate) struct WikiIndex {
+	/// `dir/page` (no extension)
+	by_path: HashMap<String, String>,
+	/// `page` (file stem)
+	by_name: HashMap<String, (usize, String)>,
+}
+
+impl WikiIndex {
+	/// Index every markdown file (any extension routed to the markdown renderer) `get_toc` would
+	/// list under `public_path`.
+	///
+	/// PERF: Built from the walk cached for the sidebar ([`super::markdown::site_files`]), so a page
+	/// with wiki links does not walk `public_path` again.
+	pub(crate) fn build(c: &Cofg) -> crate::error::AppResult<Self> {
+		let mut index = Self::default();
+		for rel in super::markdown::site_files(c)? {
+			if !super::markdown::is_markdown(&rel, c) {
+				continue;
+			}
+			let rel_str = rel.to_string_lossy().replace('\\', "/");
+			let url = format!(
+				"/{}",
+				percent_encoding::utf8_percent_encode(&rel_str, super::markdown::NON_ALPHANUMERIC)
+			);
+			let key = strip_markdown_ext(&rel_str, c).to_lowercase();
+			let depth = rel.components().count();
+			let name = key.rsplit('/').next().unwrap_or(&key).to_string();
+			index.by_path.insert(key, url.clone());
+			match index.by_name.get(&name) {
+				Some((d, _)) if *d <= depth => {}
+				_ => {
+					index.by_name.insert(name, (depth, url));
+				}
+			}
+		}
+		Ok(index)
+	}
+
+	/// URL for `target` (`Page`, `dir/Page`, `Page.md`, optionally `#Section`).
+	pub(crate) fn resolve(&self, target: &str, c: &Cofg) -> Option<String> {
+		let (page, section) = match target.split_once('#') {
+			Some((page, section)) => (page.trim(), Some(section.trim())),
+			None => (target.trim(), None),
+		};
+		let anchor = section
+			.map(|s| format!("#{}", super::heading::slugify(s)))
+			.unwrap_or_default();
+		if page.is_empty() {
+			// `[[#Section]]` links within the current page
+			return section.map(|_| anchor);
+		}
+		let key = strip_markdown_ext(page.trim_start_matches('/'), c).to_lowercase();
+		self.by_path
+			.get(&key)
+			.or_else(|| self.by_name.get(&key).map(|(_, url)| url))
+			.map(|url| format!("{url}{anchor}"))
+	}
+}
+
+/// `path` without its extension if that extension is rendered as markdown (`page.md` → `page`).
+fn strip_markdown_ext<'a>(path: &'a str, c: &Cofg) -> &'a str {
+	match path.rsplit_once('.') {
+		Some((stem, _)) if super::markdown::is_markdown(Path::new(path), c) => stem,
+		_ => path,
+	}
+}
+
+/// `[[...]]` comes out of markdown_ppp as a reference whose only label item is another reference.
+fn wiki_content(link: &LinkReference) -> Option<&[Inline]> {
+	match link.label.as_slice() {
+		[Inline::LinkReference(inner)] => Some(&inner.label),
+		_ => None,
+	}
+}
+
+/// Split `[[target|label]]` content at the first `|` found in a text node.
+fn split_label(content: &[Inline]) -> (String, Vec<Inline>) {
+	for (i, inline) in content.iter().enumerate() {
+		if let Inline::Text(text) = inline
+			&& let Some((before, after)) = text.split_once('|')
+		{
+			let mut target = super::heading::plain_text(&content[..i]);
+			target.push_str(before);
+			let mut label = Vec::new();
+			if !after.is_empty() {
+				label.push(Inline::Text(after.to_string()));
+			}
+			label.extend_from_slice(&content[i + 1..]);
+			return (target, label);
+		}
+	}
+	let target = super::heading::plain_text(content);
+	(target.clone(), vec![Inline::Text(target)])
+}
+
+/// Rewrite wiki links in `doc`; the index is only built if the page actually contains one.
+pub(crate) fn render(doc: Document, c: &Cofg) -> Document {
+	let mut index: Option<WikiIndex> = None;
+	super::inline::expand_inlines(doc, |inline| {
+		let Inline::LinkReference(link) = inline else {
+			return Err(inline);
+		};
+		let Some(content) = wiki_content(&link) else {
+			return Err(Inline::LinkReference(link));
+		};
+		let (target, label) = split_label(content);
+		let index = index.get_or_insert_with(|| {
+			WikiIndex::build(c).unwrap_or_else(|e| {
+				log::warn!("wiki link index: {e}");
+				WikiIndex::default()
+			})
+		});
+		Ok(match index.resolve(&target, c) {
+			Some(destination) => vec![Inline::Link(Link {
+				destination,
+				title: None,
+				children: label,
+			})],
+			None => {
+				log::warn!("unresolved wiki link: [[{target}]]");
+				let mut out = vec![Inline::Html(format!(
+					r#"<span class="{MISSING_CLASS}" title="{}">"#,
+					handlebars::html_escape(&target)
+				))];
+				out.extend(label);
+				out.push(Inline::Html("</span>".to_string()));
+				out
+			}
+		})
+	})
+}
@@ -0,0 +1,157 @@
+//! Redirect and rewrite rules (`redirects`)
+//!
+//! WHY: Reorganizing `public_path` breaks old URLs; stub files that only redirect clutter the tree.
+//! Rules are checked in order before `main_req` resolves the path; the first match either answers
+//! with a redirect (301/302/307/308) or rewrites the path internally and resolution continues with
+//! the new one (rewrites are not re-evaluated). A query string in a rewrite target is split off
+//! before the path lookup and put ahead of the request's own.
+//!
+//! Patterns see the request path as sent (percent-encoded), so substituted captures stay valid in a
+//! `Location` header. `exact` and `prefix` compare whole segments (`prefix` appends the rest of the
+//! path to `to`); `glob` (wax) and `regex` (whole path) substitute `$1` / `${name}` in `to`.
+//!
+//! 中文：重新導向與內部改寫規則，於解析路徑前依序比對（完全相符、前綴、glob、正規表示式），支援擷取群組替換、保留查詢字串；改寫目標可含查詢字串。
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use actix_web::http::StatusCode;
+use log::{debug, warn};
+use regex::Regex;
+use wax::{Glob, Program as _};
+
+use crate::cofg::config::{Cofg, RedirectMatch};
+
+/// What `main_req` should do with the request.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+	Redirect {
+		status: StatusCode,
+		location: String,
+	},
+	/// Continue with this path and query string (`to`'s own query first, then the request's)
+	Rewrite { path: String, query: String },
+}
+
+enum Pattern {
+	Glob(Glob<'static>),
+	Regex(Regex),
+}
+
+/// Compiled `glob` / `regex` patterns by (kind, source); `None` if invalid (warned once).
+type Compiled = HashMap<(RedirectMatch, String), Option<Arc<Pattern>>>;
+
+static COMPILED: LazyLock<RwLock<Compiled>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+fn compile(kind: RedirectMatch, from: &str) -> Option<Arc<Pattern>> {
+	let key = (kind, from.to_string());
+	if let Ok(cache) = COMPILED.read()
+		&& let Some(pattern) = cache.get(&key)
+	{
+		return pattern.clone();
+	}
+	let pattern = match kind {
+		RedirectMatch::Glob => Glob::new(from.trim_start_matches('/'))
+			.map(|g| Pattern::Glob(g.into_owned()))
+			.map_err(|e| e.to_string()),
+		_ => Regex::new(&format!("^(?:{from})$"))
+			.map(Pattern::Regex)
+			.map_err(|e| e.to_string()),
+	}
+	.inspect_err(|e| warn!("redirects: `{from}`: {e}"))
+	.ok()
+	.map(Arc::new);
+	if let Ok(mut cache) = COMPILED.write() {
+		cache.insert(key, pattern.clone());
+	}
+	pattern
+}
+
+/// `to` with `$n` / `${n}` replaced by glob captures (`$0` is the whole match).
+fn substitute(to: &str, captures: &[&str]) -> String {
+	static VAR: LazyLock<Option<Regex>> =
+		LazyLock::new(|| Regex::new(r"\$(?:(\d+)|\{(\d+)\})").ok());
+	let Some(var) = VAR.as_ref() else {
+		return to.to_string();
+	};
+	var.replace_all(to, |caps: &regex::Captures| {
+		caps.get(1)
+			.or_else(|| caps.get(2))
+			.and_then(|n| n.as_str().parse::<usize>().ok())
+			.and_then(|n| captures.get(n))
+			.copied()
+			.unwrap_or_default()
+			.to_string()
+	})
+	.into_owned()
+}
+
+/// `to` for `path` if `from` (of `kind`) matches it.
+fn target(kind: RedirectMatch, from: &str, to: &str, path: &str) -> Option<String> {
+	match kind {
+		RedirectMatch::Exact => (path == from).then(|| to.to_string()),
+		RedirectMatch::Prefix => {
+			let rest = path.strip_prefix(from.trim_end_matches('/'))?;
+			(rest.is_empty() || rest.starts_with('/'))
+				.then(|| format!("{}{rest}", to.trim_end_matches('/')))
+		}
+		RedirectMatch::Glob | RedirectMatch::Regex => match compile(kind, from)?.as_ref() {
+			Pattern::Glob(glob) => {
+				let candidate = wax::CandidatePath::from(path.trim_start_matches('/'));
+				let matched = glob.matched(&candidate)?;
+				let captures: Vec<&str> = (0..=glob.captures().count())
+					.map(|i| matched.get(i).unwrap_or_default())
+					.collect();
+				Some(substitute(to, &captures))
+			}
+			Pattern::Regex(regex) => {
+				let caps = regex.captures(path)?;
+				let mut out = String::new();
+				caps.expand(to, &mut out);
+				Some(out)
+			}
+		},
+	}
+}
+
+/// First `redirects` rule matching the request path `path` (with `query`, without `?`).
+pub(crate) fn resolve(path: &str, query: &str, c: &Cofg) -> Option<Action> {
+	for rule in &c.redirects {
+		let Some(to) = target(rule.kind, &rule.from, &rule.to, path) else {
+			continue;
+		};
+		debug!("redirects: {path} => {to} ({:?})", rule.kind);
+		if rule.rewrite {
+			let (to, to_query) = to.split_once('?').unwrap_or((&to, ""));
+			let path = if to.starts_with('/') {
+				to.to_string()
+			} else {
+				format!("/{to}")
+			};
+			let query = [to_query, query]
+				.into_iter()
+				.filter(|q| !q.is_empty())
+				.collect::<Vec<_>>()
+				.join("&");
+			return Some(Action::Rewrite { path, query });
+		}
+		let status = match StatusCode::from_u16(rule.status) {
+			Ok(status) if [301, 302, 307, 308].contains(&rule.status) => status,
+			_ => {
+				warn!(
+					"redirects: `{}`: unsupported status {}",
+					rule.from, rule.status
+				);
+				continue;
+			}
+		};
+		let location = if rule.query && !query.is_empty() {
+			let sep = if to.contains('?') { '&' } else { '?' };
+			format!("{to}{sep}{query}")
+		} else {
+			to
+		};
+		return Some(Action::Redirect { status, location });
+	}
+	None
+}
@@ -1,14 +1,21 @@
 use percent_encoding::percent_decode_str;
-use std::{fs::read_to_string, path::Path};
+use std::path::{Path, PathBuf};
 
 use actix_files::NamedFile;
 use actix_web::{Responder, http::header, mime};
 use log::{debug, error, warn};
 
 use crate::{
-	cofg::config::Cofg,
-	error::AppResult,
-	parser::{markdown::get_toc, md2html},
+	cofg::config::{Cofg, DirectoryView},
+	headers,
+	parser::{
+		PageContext, directory, ignore,
+		markdown::get_nav,
+		media, render_page,
+		renderer::{self, Renderer},
+		templating,
+	},
+	redirect,
 };
 
 /// return `500 INTERNAL_SERVER_ERROR` with header plaintext utf-8
@@ -20,9 +27,8 @@ pub(crate) fn server_error(err_text: String) -> actix_web::HttpResponse {
 
 /// Prefer configured 404 page if available, otherwise a plain-text 404 response.
 /// Respects configuration layering: checks local path first, then XDG config directory.
-async fn respond_404(req: &actix_web::HttpRequest) -> actix_web::HttpResponse {
+async fn respond_404(req: &actix_web::HttpRequest, c: &Cofg) -> actix_web::HttpResponse {
 	use actix_web::http::StatusCode;
-	let c = &Cofg::get(false);
 	let page_404_path = c.resolve_page_404_path();
 	match actix_files::NamedFile::open_async(&page_404_path).await {
 		Ok(file) => {
@@ -42,76 +48,141 @@ async fn respond_404(req: &actix_web::HttpRequest) -> actix_web::HttpResponse {
 	}
 }
 
-/// Render a markdown file into HTML via `md2html` and return an HTTP response.
+/// Collect request-derived template values shared by every rendered page.
+///
+/// Keys: `breadcrumbs` (from `rel`), `query` (decoded query parameters, after any rewrite) and
+/// `user` (Basic-auth
+/// user name, only when `http_base_authentication` is enabled so the middleware has verified it).
+///
+/// WHY: Templates need navigation and request info without each handler re-deriving it.
+fn request_context(
+	req: &actix_web::HttpRequest,
+	rel: &Path,
+	query: &std::collections::HashMap<String, String>,
+	c: &Cofg,
+) -> PageContext {
+	use actix_web::http::header::Header as _;
+	use actix_web_httpauth::headers::authorization::{Authorization, Basic};
+
+	let mut page = PageContext::default();
+	page.values
+		.insert("breadcrumbs".into(), templating::breadcrumbs(rel));
+
+	page.values.insert(
+		"query".into(),
+		serde_json::to_value(query).unwrap_or_default(),
+	);
+
+	if c.middleware.http_base_authentication.enable
+		&& let Ok(auth) = Authorization::<Basic>::parse(req)
+	{
+		page.values
+			.insert("user".into(), auth.as_ref().user_id().to_string().into());
+	}
+	page
+}
+
+/// Render a file with its configured [`Renderer`] into the HTML template and return an HTTP response.
+///
+/// Adds `file`, `nav`, `prev` and `next` on top of `request_context`.
 ///
 /// Params:
-/// - `req_path`: absolute canonical path to the markdown file
+/// - `req`: current request (auth context)
+/// - `req_path`: absolute canonical path to the file
 /// - `public_root`: absolute canonical root of `public_path` for fast `strip_prefix`
+/// - `renderer`: picked by `renderer::for_path`
+/// - `query`: decoded query parameters (of the rewritten request, if any)
 /// - `c`: read-only server configuration
 ///
 /// Note: `public_root` is passed from caller to avoid recomputing `canonicalize()` in hot paths.
-fn render_markdown_to_html_response(
+fn render_file_to_html_response(
+	req: &actix_web::HttpRequest,
 	req_path: &Path,
 	public_root: &Path,
+	renderer: &dyn Renderer,
+	query: &std::collections::HashMap<String, String>,
 	c: &Cofg,
-) -> AppResult<actix_web::HttpResponse> {
+) -> actix_web::HttpResponse {
 	use actix_web::{HttpResponseBuilder, http::StatusCode};
-	match read_to_string(req_path) {
-		Ok(md_source) => {
-			// Render Markdown directly into the HTML template with path context.
-			let rel = req_path
-				.strip_prefix(public_root)
-				.unwrap_or_else(|e| {
-					warn!("{e}");
-					req_path
-				})
-				.to_path_buf();
-
-			match md2html(md_source, c, vec![format!("path:{}", rel.display())]) {
-				Ok(html) => Ok(HttpResponseBuilder::new(StatusCode::OK)
-					.append_header(header::ContentType(mime::TEXT_HTML_UTF_8))
-					.body(html)),
-				Err(err) => {
-					warn!("{err}");
-					Ok(server_error(err.to_string()))
-				}
+	let rel = req_path
+		.strip_prefix(public_root)
+		.unwrap_or_else(|e| {
+			warn!("{e}");
+			req_path
+		})
+		.to_path_buf();
+
+	let mut page = request_context(req, &rel, query, c);
+	page.values
+		.insert("file".into(), templating::file_context(req_path));
+	page.source = Some(req_path.to_path_buf());
+	// A broken walk should not take the page down with it; templates guard on `nav`.
+	match get_nav(c, &rel) {
+		Ok(nav) => {
+			if let Ok(serde_json::Value::Object(nav)) = serde_json::to_value(nav) {
+				page.values.extend(nav);
 			}
 		}
+		Err(err) => warn!("nav: {err}"),
+	}
+
+	let html = renderer.render(req_path, c, &page).and_then(|rendered| {
+		render_page(c, vec![format!("path:{}", rel.display())], page, rendered)
+	});
+	match html {
+		Ok(html) => HttpResponseBuilder::new(StatusCode::OK)
+			.append_header(header::ContentType(mime::TEXT_HTML_UTF_8))
+			.body(html),
 		Err(err) => {
-			warn!("{err}: {}", err.kind());
-			Ok(server_error(err.to_string()))
+			warn!("{err}");
+			server_error(err.to_string())
 		}
 	}
 }
 
-/// Render a directory TOC via `get_toc` + `md2html` into the HTML template shell.
+/// Render a directory page into the HTML template shell: the TOC (below the directory's README),
+/// the index table, or a gallery / media view (`directory.view`, `?view=`).
 ///
 /// Params:
+/// - `req`: current request (auth context)
 /// - `dir_abs`: absolute canonical directory path whose TOC will be generated
+/// - `rel`: `dir_abs` relative to `public_path` (empty for the root)
 /// - `ctx_label`: logical label for both TOC title and the `path:toc:<label>` context value
+/// - `query`: decoded query parameters (of the rewritten request, if any)
 /// - `c`: read-only server configuration
 fn render_toc_to_html_response(
+	req: &actix_web::HttpRequest,
 	dir_abs: &Path,
+	rel: &Path,
 	ctx_label: &str,
+	query: &std::collections::HashMap<String, String>,
 	c: &Cofg,
 ) -> actix_web::HttpResponse {
 	use actix_web::{HttpResponseBuilder, http::StatusCode};
 	let label = if ctx_label.is_empty() { "?" } else { ctx_label };
 	debug!("{}", label);
-	let toc = get_toc(dir_abs, c, Some(label.to_string()));
-	match toc {
-		Ok(v) => {
-			let r = md2html(v, c, vec![format!("path:toc:{label}")]);
-			match r {
-				Ok(html) => HttpResponseBuilder::new(StatusCode::OK)
-					.append_header(header::ContentType(mime::TEXT_HTML_UTF_8))
-					.body(html),
-				Err(err) => {
-					warn!("{err}");
-					server_error(err.to_string())
-				}
+	let page = request_context(req, rel, query, c);
+	let view = query.get("view").map(String::as_str);
+	let r = match directory::listing(dir_abs, c) {
+		Ok(listing) => match directory::choose(&listing, c, view) {
+			DirectoryView::List => {
+				let rendered = directory::render_index(label, dir_abs, rel, &listing);
+				render_page(c, vec![format!("path:toc:{label}")], page, rendered)
 			}
-		}
+			view @ (DirectoryView::Gallery | DirectoryView::Media) => {
+				let rendered = media::render_directory(label, rel, &listing, view, c);
+				render_page(c, vec![format!("path:toc:{label}")], page, rendered)
+			}
+			_ => directory::render_toc(label, dir_abs, c, &page).and_then(|rendered| {
+				render_page(c, vec![format!("path:toc:{label}")], page, rendered)
+			}),
+		},
+		Err(err) => Err(err),
+	};
+	match r {
+		Ok(html) => HttpResponseBuilder::new(StatusCode::OK)
+			.append_header(header::ContentType(mime::TEXT_HTML_UTF_8))
+			.body(html),
 		Err(err) => {
 			warn!("{err}");
 			server_error(err.to_string())
@@ -119,23 +190,149 @@ fn render_toc_to_html_response(
 	}
 }
 
+/// Whether `/__highlight.css` is ours: class-mode highlighting is on in the current config.
+#[cfg(feature = "highlight")]
+fn highlight_class_mode(_: &actix_web::guard::GuardContext) -> bool {
+	use crate::cofg::config::HighlightMode;
+	let c = &Cofg::get(false).markdown.highlight;
+	c.enable && c.mode == HighlightMode::Class
+}
+
+/// Serve the stylesheet for class-mode code highlighting (`markdown.highlight`).
+///
+/// Registered ahead of `main_req` but guarded by [`highlight_class_mode`]: otherwise the request
+/// falls through, so a `__highlight.css` in `public_path` stays reachable. `headers` rules apply as
+/// they do in `main_req`.
+#[cfg(feature = "highlight")]
+#[actix_web::get("/__highlight.css", guard = "highlight_class_mode")]
+pub(crate) async fn highlight_css(req: actix_web::HttpRequest) -> actix_web::HttpResponse {
+	let c = &Cofg::get(false);
+	let mut res = match crate::parser::highlight::theme_css(&c.markdown.highlight) {
+		Some(css) => actix_web::HttpResponse::Ok()
+			.insert_header(header::ContentType(mime::TEXT_CSS_UTF_8))
+			.body(css),
+		None => server_error(format!(
+			"unknown highlight theme: {}",
+			c.markdown.highlight.theme
+		)),
+	};
+	headers::apply(req.path(), &mut res, c);
+	res
+}
+
+/// Serve a downscaled copy for `?w=<width>` (`markdown.images.variants`) or `?thumb` (gallery).
+///
+/// `None` → serve the original (no/unknown `w`, width not configured or not smaller, image
+/// already thumbnail-sized or not decodable).
+#[cfg(feature = "images")]
+async fn image_variant_response(
+	req: &actix_web::HttpRequest,
+	req_path: &Path,
+	c: &Cofg,
+	query: &std::collections::HashMap<String, String>,
+) -> Option<actix_web::HttpResponse> {
+	let thumb = query.contains_key("thumb");
+	let width = query.get("w").and_then(|w| w.parse::<u32>().ok());
+	if !thumb && width.is_none() {
+		return None;
+	}
+	let (c, src) = (c.clone(), req_path.to_path_buf());
+	// Decoding/resizing is CPU-bound; keep it off the async workers.
+	let variant = match actix_web::web::block(move || match width {
+		_ if thumb => crate::parser::images::thumbnail(&c, &src),
+		Some(width) => crate::parser::images::variant(&c, &src, width),
+		None => Ok(None),
+	})
+	.await
+	{
+		Ok(Ok(variant)) => variant?,
+		Ok(Err(err)) => {
+			warn!("image variant: {err}");
+			return None;
+		}
+		Err(err) => {
+			warn!("image variant: {err}");
+			return None;
+		}
+	};
+	match NamedFile::open_async(variant).await {
+		Ok(file) => Some(file.into_response(req)),
+		Err(err) => {
+			warn!("image variant: {err}");
+			None
+		}
+	}
+}
+
+/// Whether `path` (canonical, under `public_path`) is ignored and `toc.ig_deny` forbids serving it.
+fn denied(path: &Path, public_path: &Path, c: &Cofg) -> bool {
+	c.toc.ig_deny && ignore::Matcher::new(public_path, c).is_ignored(path, path.is_dir())
+}
+
+/// `spa` file for the missing path `decoded` (percent-decoded request path), by longest prefix.
+///
+/// Prefixes match whole segments (`/app` covers `/app/users/42`, not `/apple`); `None` when no
+/// prefix applies or the configured file does not exist.
+pub(crate) fn spa_fallback(decoded: &str, public_path: &Path, c: &Cofg) -> Option<PathBuf> {
+	c.spa
+		.iter()
+		.map(|(prefix, file)| (prefix.trim_end_matches('/'), file))
+		.filter(|(prefix, _)| {
+			decoded
+				.strip_prefix(prefix)
+				.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+		})
+		.max_by_key(|(prefix, _)| prefix.len())
+		.map(|(_, file)| public_path.join(file.trim_start_matches('/')))
+		.filter(|file| file.is_file())
+}
+
 #[actix_web::get("/{filename:.*}")]
 /// Fallback handler for any path (captures `/{filename:.*}`) serving either a rendered markdown
 /// or static file; returns custom 404 page if missing.
 ///
 /// Flow:
-/// 1. Resolve absolute disk path under `public_path`
-/// 2. If missing → attempt meta/404.html else plain text 404
-/// 3. If markdown → read + `md2html` with `path:` context
-/// 4. Else stream static file
+/// 1. `redirects`: redirect, or rewrite the path; then resolve absolute disk path under `public_path`
+/// 2. If missing → the `spa` file of a matching prefix (200); missing or ignored with `toc.ig_deny`
+///    (the request path or the `spa` file) → attempt meta/404.html else plain text 404
+/// 3. Directory with a `directory.index` file (and no `?view`) → redirect to `dir/`, then serve
+///    the index as the requested file (404 if the index itself is ignored with `toc.ig_deny`)
+/// 4. Unless `?raw`: `?view=player` on audio/video → player page, `?view` or `?view=source` →
+///    source viewer; else the renderer configured for the extension (`renderers`) → render into
+///    `html-t`
+/// 5. Else stream static file
+/// 6. Add the `headers` rules matching the served path (after a rewrite or `spa` fallback) to the
+///    response
 ///
 /// WHY: Unify file resolution & markdown rendering into one route while keeping index logic
 /// separate for TOC special-case.
-/// 中文：統一路徑處理；Markdown 即時轉換，其餘走靜態檔。
+/// 中文：統一路徑處理；有對應渲染器的檔案即時轉換，其餘走靜態檔。
 pub(crate) async fn main_req(req: actix_web::HttpRequest) -> impl actix_web::Responder {
 	debug!("{req:?}");
 
-	let c = &Cofg::get(false);
+	// An app-level config (`App::app_data`) takes precedence over the global one.
+	let c = &req
+		.app_data::<Cofg>()
+		.cloned()
+		.unwrap_or_else(|| Cofg::get(false));
+	let mut served = percent_decode_str(req.path())
+		.decode_utf8_lossy()
+		.into_owned();
+	let mut res = serve(&req, &mut served, c).await;
+	// `headers` apply to whatever the request produced, 404 included.
+	headers::apply(&served, &mut res, c);
+	res
+}
+
+/// Body of [`main_req`]: the response before `headers` are added.
+///
+/// `served` starts as the percent-decoded request path and is replaced by the path actually served
+/// after a rewrite or `spa` fallback, so `headers` rules see the file they apply to.
+async fn serve(
+	req: &actix_web::HttpRequest,
+	served: &mut String,
+	c: &Cofg,
+) -> actix_web::HttpResponse {
 	let public_path = &Path::new(&c.public_path)
 		.canonicalize()
 		.unwrap_or_else(|e| {
@@ -147,13 +344,34 @@ pub(crate) async fn main_req(req: actix_web::HttpRequest) -> impl actix_web::Res
 		});
 	debug!("public_path={}", public_path.display());
 
+	// `redirects`: answer with a redirect, or carry on with a rewritten path.
+	let path = match redirect::resolve(req.path(), req.query_string(), c) {
+		Some(redirect::Action::Redirect { status, location }) => {
+			debug!("redirect => {location}");
+			return actix_web::HttpResponse::build(status)
+				.append_header((header::LOCATION, location))
+				.finish();
+		}
+		Some(redirect::Action::Rewrite { path, query }) => Some((path, query)),
+		None => None,
+	};
+	let rewritten = path.is_some();
+	let (path, query_string) = path
+		.as_ref()
+		.map_or((req.path(), req.query_string()), |(path, query)| {
+			(path.as_str(), query.as_str())
+		});
+
 	// Resolve the target path under the configured public root.
 	// path is "/" -> ""
 	// WHY: 8c648dc7d9dcbf6769238ead8810aa7f324aaf7d
 	// Percent-decode the request path so encoded characters (eg. "%2E") map to
 	// actual filesystem names (eg. "."). This prevents canonicalize() from
 	// failing when clients send encoded paths.
-	let decoded = percent_decode_str(req.path()).decode_utf8_lossy();
+	let decoded = percent_decode_str(path).decode_utf8_lossy();
+	if rewritten {
+		*served = decoded.to_string();
+	}
 	let filename_str = if decoded == "/" {
 		".".to_string()
 	} else {
@@ -165,7 +383,24 @@ pub(crate) async fn main_req(req: actix_web::HttpRequest) -> impl actix_web::Res
 
 	if !req_path_buf.exists() {
 		debug!("{}:!exists", req_path_buf.display());
-		return respond_404(&req).await;
+		// The fallback file is served as-is, so it gets the same confinement and ignore checks.
+		if let Some(file) = spa_fallback(&decoded, public_path, c)
+			.and_then(|file| file.canonicalize().ok())
+			.filter(|file| file.starts_with(public_path) && !denied(file, public_path, c))
+		{
+			debug!("spa => {}", file.display());
+			if let Ok(rel) = file.strip_prefix(public_path) {
+				*served = format!("/{}", rel.to_string_lossy());
+			}
+			return match NamedFile::open_async(&file).await {
+				Ok(file) => file.into_response(req),
+				Err(err) => {
+					warn!("{err}: {}", file.display());
+					server_error(err.to_string())
+				}
+			};
+		}
+		return respond_404(req, c).await;
 	}
 
 	let req_path = &(match req_path_buf.canonicalize() {
@@ -190,41 +425,78 @@ pub(crate) async fn main_req(req: actix_web::HttpRequest) -> impl actix_web::Res
 		}
 	};
 
-	if req_path == public_path {
-		let index_file = public_path.join("index.html");
-		if index_file.exists() {
-			let f = read_to_string(index_file);
-			match f {
-				Ok(value) => {
-					debug!("index exists=>show file");
-					return actix_web::HttpResponseBuilder::new(actix_web::http::StatusCode::OK)
-						.append_header(header::ContentType(mime::TEXT_HTML_UTF_8))
-						.body(value);
-				}
-				Err(err) => {
-					warn!("{err}");
-					return server_error(err.to_string());
-				}
-			}
-		}
+	if denied(req_path, public_path, c) {
+		debug!("{}: ignored", req_path.display());
+		return respond_404(req, c).await;
 	}
+
 	if !req_path.exists() {
 		debug!("{}:!exists", req_path.display());
-		return respond_404(&req).await;
+		return respond_404(req, c).await;
 	}
 
-	let is_md = req_path.extension().and_then(|v| v.to_str()) == Some("md");
-	if is_md {
-		debug!("is md");
-		// Render Markdown to HTML and return.
-		match render_markdown_to_html_response(req_path, public_path, c) {
-			Ok(res) => res,
-			Err(err) => server_error(err.to_string()),
+	// `?raw` skips rendering and serves the original bytes; `?view` / `?view=source` shows any
+	// file as source, `?view=player` audio/video in a player.
+	let query = actix_web::web::Query::<std::collections::HashMap<String, String>>::from_query(
+		query_string,
+	)
+	.map(|q| q.into_inner())
+	.unwrap_or_else(|e| {
+		debug!("ignore malformed query string: {e}");
+		Default::default()
+	});
+
+	// Directory index (`directory.index`) unless a directory view is asked for explicitly.
+	let index = req_path
+		.is_dir()
+		.then(|| directory::index_file(req_path, c))
+		.flatten()
+		.filter(|_| !query.contains_key("view"));
+	if let Some(index) = &index
+		&& !rewritten
+		&& !req.path().ends_with('/')
+	{
+		// Like other servers: relative links in the index resolve against the directory.
+		let mut location = format!("{}/", req.path());
+		if !req.query_string().is_empty() {
+			location = format!("{location}?{}", req.query_string());
+		}
+		debug!("index {} => {location}", index.display());
+		return actix_web::HttpResponse::MovedPermanently()
+			.append_header((header::LOCATION, location))
+			.finish();
+	}
+	let req_path = index.as_deref().unwrap_or(req_path);
+	// The index may be ignored even though its directory is not.
+	if index.is_some() && denied(req_path, public_path, c) {
+		debug!("{}: ignored", req_path.display());
+		return respond_404(req, c).await;
+	}
+
+	let renderer = if !req_path.is_file() || query.contains_key("raw") {
+		None
+	} else {
+		match query.get("view").map(String::as_str) {
+			Some("player")
+				if media::kind(req_path).is_some_and(|k| k != media::MediaKind::Image) =>
+			{
+				renderer::builtin("player")
+			}
+			Some("" | "source") => renderer::builtin("source"),
+			_ => renderer::for_path(req_path, c),
 		}
+	};
+	if let Some(renderer) = renderer {
+		debug!("render");
+		render_file_to_html_response(req, req_path, public_path, renderer, &query, c)
 	} else if req_path.is_file() {
 		debug!("no md");
+		#[cfg(feature = "images")]
+		if let Some(res) = image_variant_response(req, req_path, c, &query).await {
+			return res;
+		}
 		match NamedFile::open_async(req_path).await {
-			Ok(file) => file.into_response(&req),
+			Ok(file) => file.into_response(req),
 			Err(err) => {
 				warn!("{err}: {}", err.kind());
 				server_error(err.to_string())
@@ -235,9 +507,9 @@ pub(crate) async fn main_req(req: actix_web::HttpRequest) -> impl actix_web::Res
 		let label = req_strip_prefix_path.to_string_lossy();
 		if req_path == public_path {
 			// if is index
-			render_toc_to_html_response(req_path, "index", c)
+			render_toc_to_html_response(req, req_path, req_strip_prefix_path, "index", &query, c)
 		} else {
-			render_toc_to_html_response(req_path, &label, c)
+			render_toc_to_html_response(req, req_path, req_strip_prefix_path, &label, &query, c)
 		}
 	} else {
 		error!("{}: not file and dir", req_strip_prefix_path.display());
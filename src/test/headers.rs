@@ -0,0 +1,78 @@
+//! Custom response header tests
+//!
+//! WHY: Validate `headers` rule matching:
+//! - path globs, extensions and their combination
+//! - rule order (later wins) and invalid headers
+//! - headers replacing those already on a response
+
+use actix_web::{HttpResponse, http::header};
+use simple_test_case::test_case;
+
+use crate::headers::{apply, for_path};
+use crate::test::support::cofg_with;
+
+const RULES: &str = r#"
+headers:
+  - path: "assets/**/*.{js,css}"
+    headers:
+      Cache-Control: public, max-age=31536000, immutable
+  - path: "runbooks/**"
+    headers:
+      Cache-Control: no-store
+      X-Robots-Tag: noindex
+  - ext: [PDF]
+    headers:
+      Content-Disposition: attachment
+  - path: "runbooks/**"
+    ext: [pdf]
+    headers:
+      Content-Disposition: inline
+  - headers:
+      Bad Name: x
+"#;
+
+#[test_case("/assets/app.3f2a.js", &[("cache-control", "public, max-age=31536000, immutable")] ; "glob")]
+#[test_case("/assets/img/logo.png", &[] ; "glob extension mismatch")]
+#[test_case("/runbooks/deploy.md", &[("cache-control", "no-store"), ("x-robots-tag", "noindex")] ; "several headers")]
+#[test_case("/files/a.pdf", &[("content-disposition", "attachment")] ; "extension case-insensitive")]
+#[test_case("/runbooks/a.pdf", &[("cache-control", "no-store"), ("content-disposition", "inline"), ("x-robots-tag", "noindex")] ; "later rule wins")]
+#[test_case("/", &[] ; "no rule")]
+#[test]
+fn test_headers_for_path(path: &str, expected: &[(&str, &str)]) {
+	let c = cofg_with(RULES);
+	let mut res = HttpResponse::Ok().finish();
+	apply(path, &mut res, &c);
+
+	let mut got: Vec<(String, String)> = res
+		.headers()
+		.iter()
+		.map(|(k, v)| (k.to_string(), v.to_str().unwrap().to_string()))
+		.collect();
+	got.sort();
+	let expected: Vec<(String, String)> = expected
+		.iter()
+		.map(|(k, v)| (k.to_string(), v.to_string()))
+		.collect();
+	assert_eq!(got, expected);
+}
+
+#[test]
+fn test_headers_replace_existing() {
+	let c = cofg_with(RULES);
+	let mut res = HttpResponse::Ok()
+		.insert_header((header::CACHE_CONTROL, "max-age=60"))
+		.finish();
+	apply("/runbooks/x.md", &mut res, &c);
+	assert_eq!(res.headers().get_all(header::CACHE_CONTROL).count(), 1);
+	assert_eq!(
+		res.headers().get(header::CACHE_CONTROL).unwrap(),
+		"no-store"
+	);
+}
+
+#[test]
+fn test_headers_default_empty() {
+	let c = crate::cofg::config::Cofg::default();
+	assert!(c.headers.is_empty());
+	assert!(for_path("/any/file.js", &c).is_empty());
+}
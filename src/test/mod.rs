@@ -9,6 +9,7 @@
 //! - Error handling and status code mapping
 //! - Main module utilities and version info
 //! - Request handler behaviors
+//! - Redirect / rewrite rules and custom response headers
 //!
 //! WHY: Organize tests by functional area matching the copilot-instructions.md structure,
 //! making it easy to navigate and extend test coverage for specific features.
@@ -18,9 +19,11 @@
 pub(crate) mod cli;
 pub(crate) mod config;
 pub(crate) mod error;
+pub(crate) mod headers;
 pub(crate) mod integration;
 pub(crate) mod main;
 pub(crate) mod parser;
+pub(crate) mod redirect;
 pub(crate) mod request;
 pub(crate) mod security;
 pub(crate) mod support;
@@ -3,7 +3,7 @@
 //! WHY: Validate core rendering pipeline:
 //! - Markdown AST parsing works correctly
 //! - Template context assembly and type inference
-//! - md2html integration (markdown → HTML → template)
+//! - markdown_body + render_page integration (markdown → HTML → template)
 //! - TOC generation logic
 
 use markdown_ppp::ast::{
@@ -20,11 +20,30 @@ use markdown_ppp::ast::{
 };
 use simple_test_case::test_case;
 
-use crate::cofg::config::Cofg;
-use crate::parser::{markdown, md2html, templating};
+use crate::cofg::config::{Cofg, DirectoryView, HtmlPolicy, TableHeader, TocSort};
+use crate::parser::{PageContext, markdown, markdown_body, parse_source, render_page, templating};
 use crate::test::config::create_test_dir;
 use std::fs;
 
+/// Markdown source → AST as the markdown renderer parses it (built-in config).
+fn parse(md: String) -> crate::error::AppResult<Document> {
+	parse_source(md, &Cofg::default(), &mut Vec::new())
+}
+
+/// Full page for `md`: the markdown renderer's body wrapped in `html-t`, as `main_req` serves it.
+fn page_html(md: String, c: &Cofg, vars: Vec<String>) -> crate::error::AppResult<String> {
+	let page = PageContext::default();
+	let rendered = markdown_body(md, c, &page)?;
+	render_page(c, vars, page, rendered)
+}
+
+/// Math spans cut out of `md`, as [`parse_source`] does with `markdown.math` on.
+fn extract(md: &str) -> (String, Vec<crate::parser::math::MathSpan>) {
+	let mut spans = Vec::new();
+	let out = crate::parser::math::extract_into(md, &mut spans);
+	(out, spans)
+}
+
 #[test_case(
     "# Hello World\n\nThis is a test.",
     Document {
@@ -122,7 +141,7 @@ fn main() {
 async fn test_markdown_parsing(md: &'static str, expected_ast: Document) {
 	let md = md.to_string();
 
-	let result = markdown::parser_md(md);
+	let result = parse(md);
 	assert!(result.is_ok(), "Basic markdown should parse successfully");
 
 	let ast = result.unwrap();
@@ -225,7 +244,7 @@ async fn test_set_context_value_invalid_format() {
 #[test_case("simple_markdown", "# Welcome\n\nSimple content" ; "Simple markdown")]
 #[test_case("h2_heading", "## Section\n\nContent here" ; "H2 heading")]
 #[actix_web::test]
-async fn test_md2html_basic(case: &str, md: &str) {
+async fn test_render_page_basic(case: &str, md: &str) {
 	let temp_dir = create_test_dir();
 	let template_path = temp_dir.path().join("test-template.hbs");
 
@@ -245,7 +264,7 @@ async fn test_md2html_basic(case: &str, md: &str) {
 		..Cofg::default()
 	};
 
-	let html = md2html(md.to_string(), &config, vec![]).unwrap();
+	let html = page_html(md.to_string(), &config, vec![]).unwrap();
 	match case {
 		"heading_and_text" => {
 			insta::assert_snapshot!(html, @"<!DOCTYPE html><html><body><h1>Test</h1><p>Hello world!</p></body></html>")
@@ -264,7 +283,7 @@ async fn test_md2html_basic(case: &str, md: &str) {
 #[test_case("multiple_context_vars", "# Documentation", vec!["title:Docs".to_string(), "author:Team".to_string()] ; "Multiple context vars")]
 #[test_case("no_context", "# About", vec![] ; "No context")]
 #[actix_web::test]
-async fn test_md2html_with_context(case: &str, md: &str, context_vars: Vec<String>) {
+async fn test_render_page_with_context(case: &str, md: &str, context_vars: Vec<String>) {
 	let temp_dir = create_test_dir();
 	let template_path = temp_dir.path().join("test-template-ctx.hbs");
 
@@ -284,7 +303,7 @@ async fn test_md2html_with_context(case: &str, md: &str, context_vars: Vec<Strin
 		..Cofg::default()
 	};
 
-	let html = md2html(md.to_string(), &config, context_vars).unwrap();
+	let html = page_html(md.to_string(), &config, context_vars).unwrap();
 	match case {
 		"with_title" => {
 			insta::assert_snapshot!(html, @"<!DOCTYPE html><html><head><title>Test Page</title></head><body><h1>Content</h1></body></html>")
@@ -336,7 +355,7 @@ async fn test_toc_generation(is_empty: bool, title: &str) {
 #[test_case(r#"# Test\n\n[Home](./index.md)"# ; "Single link")]
 #[actix_web::test]
 async fn test_markdown_with_links(md: &str) {
-	let result = markdown::parser_md(md.to_string());
+	let result = parse(md.to_string());
 	assert!(
 		result.is_ok(),
 		"Markdown with links should parse successfully"
@@ -346,7 +365,7 @@ async fn test_markdown_with_links(md: &str) {
 #[test_case("multiple_links", "# Links\n\n[Google](https://www.google.com)\n[Internal Link](./page.md)\n" ; "Multiple links")]
 #[test_case("single_link", "# Home\n\n[Index](./index.md)\n" ; "Single link")]
 #[actix_web::test]
-async fn test_md2html_with_links_snapshot(case: &str, md: &str) {
+async fn test_render_page_with_links_snapshot(case: &str, md: &str) {
 	let temp_dir = create_test_dir();
 	let template_path = temp_dir.path().join("links-template.hbs");
 
@@ -365,14 +384,16 @@ async fn test_md2html_with_links_snapshot(case: &str, md: &str) {
 		..Cofg::default()
 	};
 
-	let html = md2html(md.to_string(), &config, vec![]).unwrap();
+	let html = page_html(md.to_string(), &config, vec![]).unwrap();
 	match case {
 		"multiple_links" => {
-			insta::assert_snapshot!(html, @r###"<!DOCTYPE html><html><body><h1>Links</h1><p><a href="https://www.google.com">Google</a>
-<a href="./page.md">Internal Link</a></p></body></html>"###)
+			insta::assert_snapshot!(html, @r##"
+			<!DOCTYPE html><html><body><h1>Links</h1><p><a href="https://www.google.com">Google</a>
+			<a href="./page.md">Internal Link</a></p></body></html>
+			"##)
 		}
 		"single_link" => {
-			insta::assert_snapshot!(html, @"<!DOCTYPE html><html><body><h1>Home</h1><p><a href=\"./index.md\">Index</a></p></body></html>")
+			insta::assert_snapshot!(html, @r##"<!DOCTYPE html><html><body><h1>Home</h1><p><a href="./index.md">Index</a></p></body></html>"##)
 		}
 		_ => panic!("Unknown test case: {case}"),
 	}
@@ -382,7 +403,7 @@ async fn test_md2html_with_links_snapshot(case: &str, md: &str) {
 #[test_case(r#"# Single\n\n![Logo](./logo.svg)"# ; "Single image")]
 #[actix_web::test]
 async fn test_markdown_with_images(md: &str) {
-	let result = markdown::parser_md(md.to_string());
+	let result = parse(md.to_string());
 	assert!(
 		result.is_ok(),
 		"Markdown with images should parse successfully"
@@ -392,7 +413,7 @@ async fn test_markdown_with_images(md: &str) {
 #[test_case("multiple_images", "# Images\n\n![Alt text](./image.png)\n![Remote image](https://example.com/image.jpg)\n" ; "Multiple images")]
 #[test_case("single_image", "# Logo\n\n![Logo](./logo.svg)\n" ; "Single image")]
 #[actix_web::test]
-async fn test_md2html_with_images_snapshot(case: &str, md: &str) {
+async fn test_render_page_with_images_snapshot(case: &str, md: &str) {
 	let temp_dir = create_test_dir();
 	let template_path = temp_dir.path().join("images-template.hbs");
 
@@ -411,7 +432,7 @@ async fn test_md2html_with_images_snapshot(case: &str, md: &str) {
 		..Cofg::default()
 	};
 
-	let html = md2html(md.to_string(), &config, vec![]).unwrap();
+	let html = page_html(md.to_string(), &config, vec![]).unwrap();
 	match case {
 		"multiple_images" => {
 			insta::assert_snapshot!(html, @r###"<!DOCTYPE html><html><body><h1>Images</h1><p><img src="./image.png" alt="Alt text"></img>
@@ -427,7 +448,7 @@ async fn test_md2html_with_images_snapshot(case: &str, md: &str) {
 #[test_case("_2x2_table", "# Table\n\n| Column 1 | Column 2 |\n|----------|----------|\n| Cell 1   | Cell 2   |\n| Cell 3   | Cell 4   |\n" ; "2x2 table")]
 #[test_case("user_table", "# Users\n\n| Name | Age |\n|------|-----|\n| Alice | 25 |\n| Bob | 30 |\n| Carol | 28 |\n" ; "User table")]
 #[actix_web::test]
-async fn test_md2html_with_tables_snapshot(case: &str, md: &str) {
+async fn test_render_page_with_tables_snapshot(case: &str, md: &str) {
 	let temp_dir = create_test_dir();
 	let template_path = temp_dir.path().join("tables-template.hbs");
 
@@ -446,7 +467,7 @@ async fn test_md2html_with_tables_snapshot(case: &str, md: &str) {
 		..Cofg::default()
 	};
 
-	let html = md2html(md.to_string(), &config, vec![]).unwrap();
+	let html = page_html(md.to_string(), &config, vec![]).unwrap();
 	match case {
 		"_2x2_table" => {
 			insta::assert_snapshot!(html, @"<!DOCTYPE html><html><body><h1>Table</h1><table><thead><tr><th class=\"markdown-table-align-left\">Column 1</th><th class=\"markdown-table-align-left\">Column 2</th></tr></thead><tbody><tr><td class=\"markdown-table-align-left\">Cell 1</td><td class=\"markdown-table-align-left\">Cell 2</td></tr><tr><td class=\"markdown-table-align-left\">Cell 3</td><td class=\"markdown-table-align-left\">Cell 4</td></tr></tbody></table></body></html>")
@@ -507,7 +528,7 @@ async fn test_empty_template_data(case: &str, md: &str) {
 		..Cofg::default()
 	};
 
-	let html = md2html(md.to_string(), &config, vec![]).unwrap();
+	let html = page_html(md.to_string(), &config, vec![]).unwrap();
 	match case {
 		"simple_text" => insta::assert_snapshot!(html, @"<html><p>Test</p></html>"),
 		"heading_with_paragraph" => {
@@ -519,3 +540,1258 @@ async fn test_empty_template_data(case: &str, md: &str) {
 		_ => panic!("Unknown test case: {case}"),
 	}
 }
+
+#[actix_web::test]
+async fn test_context_has_version_struct() {
+	let context = templating::get_context(&Cofg::default());
+
+	let version = context.data().get("version").expect("version key");
+	assert_eq!(
+		version.get("version").and_then(|v| v.as_str()),
+		Some(env!("CARGO_PKG_VERSION"))
+	);
+	assert!(version.get("commit_hash").is_some());
+}
+
+#[test_case("", &[("root", "/")] ; "Root path")]
+#[test_case("dir/sub/a b.md", &[("root", "/"), ("dir", "/dir"), ("sub", "/dir/sub"), ("a b.md", "/dir/sub/a%20b%2Emd")] ; "Nested path")]
+#[actix_web::test]
+async fn test_breadcrumbs(rel: &str, expected: &[(&str, &str)]) {
+	let crumbs = templating::breadcrumbs(std::path::Path::new(rel));
+	let got: Vec<(&str, &str)> = crumbs
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|c| (c["name"].as_str().unwrap(), c["url"].as_str().unwrap()))
+		.collect();
+	assert_eq!(got, expected);
+}
+
+#[actix_web::test]
+async fn test_file_context_metadata() {
+	let temp_dir = create_test_dir();
+	let path = temp_dir.path().join("note.md");
+	fs::write(&path, "12345").expect("Should write note.md");
+
+	let file = templating::file_context(&path);
+
+	assert_eq!(file["name"], "note.md");
+	assert_eq!(file["size"], 5);
+	assert!(file["modified"].as_u64().is_some());
+	assert!(file["modified_http"].as_str().unwrap().ends_with("GMT"));
+}
+
+#[actix_web::test]
+async fn test_render_page_structured_context() {
+	let temp_dir = create_test_dir();
+	let template_path = temp_dir.path().join("page-template.hbs");
+
+	fs::write(
+		&template_path,
+		"{{#each breadcrumbs}}[{{name}}]{{/each}}{{query.q}}|{{{body}}}",
+	)
+	.expect("Should write template");
+
+	let config = Cofg {
+		hbs_path: template_path.to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	let mut page = PageContext::default();
+	page.values.insert(
+		"breadcrumbs".into(),
+		templating::breadcrumbs(std::path::Path::new("dir")),
+	);
+	page.values
+		.insert("query".into(), serde_json::json!({ "q": "search" }));
+
+	let rendered = markdown_body("text".to_string(), &config, &page).unwrap();
+	let html = render_page(&config, vec![], page, rendered).unwrap();
+	insta::assert_snapshot!(html, @"[root][dir]search|<p>text</p>");
+}
+
+#[test_case("a.md", None, Some("/b%2Emd") ; "First page")]
+#[test_case("b.md", Some("/a%2Emd"), Some("/sub/c%2Emd") ; "Middle page")]
+#[test_case("sub/c.md", Some("/b%2Emd"), None ; "Last page")]
+#[test_case("missing.md", None, None ; "Page not in tree")]
+#[actix_web::test]
+async fn test_nav_prev_next(current: &str, prev: Option<&str>, next: Option<&str>) {
+	let temp_dir = create_test_dir();
+	fs::create_dir(temp_dir.path().join("sub")).expect("Should create sub");
+	fs::write(temp_dir.path().join("a.md"), "# A").expect("Should write a.md");
+	fs::write(temp_dir.path().join("b.md"), "# B").expect("Should write b.md");
+	fs::write(temp_dir.path().join("sub/c.md"), "# C").expect("Should write sub/c.md");
+
+	let config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+
+	let nav = markdown::get_nav(&config, std::path::Path::new(current)).unwrap();
+
+	assert_eq!(nav.prev.as_ref().map(|l| l.url.as_str()), prev);
+	assert_eq!(nav.next.as_ref().map(|l| l.url.as_str()), next);
+	let names: Vec<&str> = nav.nav.iter().map(|i| i.name.as_str()).collect();
+	assert_eq!(names, ["a.md", "b.md", "sub"]);
+	assert_eq!(nav.nav[2].children[0].url, "/sub/c%2Emd");
+	assert_eq!(nav.nav[2].active, current.starts_with("sub/"));
+}
+
+#[test_case("---\ntitle: Intro\norder: 2\n---\n# Heading", Some("Intro"), Some(2.0) ; "Front matter")]
+#[test_case("text\n\n# First\n\n# Second", Some("First"), None ; "First h1")]
+#[test_case("Setext\n===\n", Some("Setext"), None ; "Setext h1")]
+#[test_case("```\n# not a title\n```\n## Sub", None, None ; "Fenced and h2 ignored")]
+#[test_case("---\nweight: 5\n---\nbody", None, Some(5.0) ; "Weight")]
+#[test]
+fn test_doc_meta(md: &str, title: Option<&str>, order: Option<f64>) {
+	let meta = markdown::doc_meta_of(md.to_string());
+	assert_eq!(meta.title.as_deref(), title);
+	assert_eq!(meta.order, order);
+}
+
+#[test_case(TocSort::Name ; "by name")]
+#[test_case(TocSort::Title ; "by title")]
+#[test_case(TocSort::Order ; "by order")]
+#[test_case(TocSort::Mtime ; "by mtime")]
+#[test]
+fn test_toc_titles_sort(sort: TocSort) {
+	let temp_dir = create_test_dir();
+	let files = [
+		("a.md", "---\norder: 3\n---\n# Zebra", 30),
+		("b.md", "---\ntitle: Apple\norder: 1\n---\n", 10),
+		("c.md", "no heading", 20),
+	];
+	for (name, md, age) in files {
+		let path = temp_dir.path().join(name);
+		fs::write(&path, md).expect("Should write markdown");
+		let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000 - age);
+		fs::File::options()
+			.write(true)
+			.open(&path)
+			.and_then(|f| f.set_modified(modified))
+			.expect("Should set mtime");
+	}
+
+	let mut config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	config.toc.titles = true;
+	config.toc.sort = sort;
+
+	let toc = markdown::get_toc(temp_dir.path(), &config, None).unwrap();
+	let mut labels = vec!["Zebra", "Apple", "c.md"];
+	labels.sort_by_key(|label| toc.find(&format!("[{label}]")).expect(label));
+	let expected = match sort {
+		TocSort::Name => ["Zebra", "Apple", "c.md"],
+		TocSort::Title | TocSort::Mtime => ["Apple", "c.md", "Zebra"],
+		TocSort::Order => ["Apple", "Zebra", "c.md"],
+	};
+	assert_eq!(labels, expected, "{toc}");
+}
+
+#[test_case("nodes.md", false ; "Name is not a substring match")]
+#[test_case("node/a.md", true ; "Ignored directory hides children")]
+#[test_case("sub/node/a.md", true ; "Unanchored name at any depth")]
+#[test_case("draft.md", true ; "Anchored glob")]
+#[test_case("sub/draft.md", false ; "Anchored glob only at root")]
+#[test_case("sub/x.log", true ; "Glob from ignore file")]
+#[test_case("sub/keep.log", false ; "Negated in ignore file")]
+#[test_case("x.log", false ; "Ignore file scoped to its directory")]
+#[test_case("sub/build", false ; "Directory-only rule skips files")]
+#[test_case("sub/deep/build/a.md", true ; "Directory-only rule")]
+#[test_case("sub/deep/secret.md", false ; "Deeper ignore file wins")]
+#[test]
+fn test_ignore_rules(path: &str, ignored: bool) {
+	let temp_dir = create_test_dir();
+	fs::create_dir_all(temp_dir.path().join("sub/deep")).expect("Should create sub/deep");
+	fs::write(
+		temp_dir.path().join("sub/.gitignore"),
+		"# comment\n*.log\n!keep.log\nbuild/\nsecret.md\n",
+	)
+	.expect("Should write .gitignore");
+	fs::write(temp_dir.path().join("sub/deep/.tocignore"), "!secret.md\n")
+		.expect("Should write .tocignore");
+
+	let mut config = Cofg::default();
+	config.toc.ig = vec!["node".to_string(), "/draft.*".to_string()];
+	config.toc.ig_files = vec![".gitignore".into(), ".tocignore".into()];
+
+	let mut matcher = crate::parser::ignore::Matcher::new(temp_dir.path(), &config);
+	assert_eq!(
+		matcher.is_ignored(&temp_dir.path().join(path), false),
+		ignored
+	);
+}
+
+#[test_case(&["*.md", "!keep.md"], "keep.md", false ; "Later negation re-includes")]
+#[test_case(&["*.md", "!keep.md"], "other.md", true ; "Negation is specific")]
+#[test_case(&["!keep.md", "*.md"], "keep.md", true ; "Last match wins")]
+#[test_case(&["<build-in>", "!node_modules"], "node_modules/a.md", false ; "Built-in expanded in place")]
+#[test]
+fn test_toc_ig_order(ig: &[&str], path: &str, ignored: bool) {
+	let temp_dir = create_test_dir();
+	let mut config = Cofg::default();
+	config.toc.ig = ig.iter().map(|s| s.to_string()).collect();
+	let config = config.configure_default_extensions();
+
+	let mut matcher = crate::parser::ignore::Matcher::new(temp_dir.path(), &config);
+	assert_eq!(
+		matcher.is_ignored(&temp_dir.path().join(path), false),
+		ignored
+	);
+}
+
+#[test]
+fn test_toc_ignore_files() {
+	let temp_dir = create_test_dir();
+	fs::create_dir(temp_dir.path().join("private")).expect("Should create private");
+	fs::write(temp_dir.path().join("nodes.md"), "# N").expect("Should write nodes.md");
+	fs::write(temp_dir.path().join("private/a.md"), "# A").expect("Should write a.md");
+	fs::write(temp_dir.path().join(".myhttpignore"), "private/\n").expect("Should write ignore");
+
+	let mut config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	config.toc.ig = vec!["node".to_string()];
+
+	let toc = markdown::get_toc(temp_dir.path(), &config, None).unwrap();
+	assert!(toc.contains("nodes.md"), "{toc}");
+	assert!(!toc.contains("private"), "{toc}");
+
+	let listing = crate::parser::directory::listing(temp_dir.path(), &config).unwrap();
+	assert_eq!(listing.dirs, Vec::<String>::new());
+	assert_eq!(listing.files, ["nodes.md"]);
+}
+
+fn toc_tree() -> (tempfile::TempDir, Cofg) {
+	let temp_dir = create_test_dir();
+	fs::create_dir_all(temp_dir.path().join("a/b")).expect("Should create a/b");
+	fs::write(temp_dir.path().join("top.md"), "# Top").expect("Should write top.md");
+	fs::write(temp_dir.path().join("a/one.md"), "# One").expect("Should write one.md");
+	fs::write(temp_dir.path().join("a/b/two.md"), "# Two").expect("Should write two.md");
+	let config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	(temp_dir, config)
+}
+
+#[test_case(0, 3 ; "unlimited")]
+#[test_case(1, 1 ; "top level only")]
+#[test_case(2, 2 ; "two levels")]
+#[test]
+fn test_toc_depth(depth: usize, levels: usize) {
+	let (temp_dir, mut config) = toc_tree();
+	config.toc.depth = depth;
+
+	let toc = markdown::get_toc(temp_dir.path(), &config, None).unwrap();
+	let deepest = toc
+		.lines()
+		.filter(|l| l.trim_start().starts_with("- "))
+		.map(|l| (l.len() - l.trim_start().len()) / 4 + 1)
+		.max();
+	assert_eq!(deepest, Some(levels), "{toc}");
+}
+
+#[test]
+fn test_toc_collapse() {
+	let (temp_dir, mut config) = toc_tree();
+	config.toc.collapse = true;
+	config.toc.depth = 2;
+
+	let html = markdown::get_toc_html(temp_dir.path(), &config).unwrap();
+	insta::assert_snapshot!(html, @r#"<nav class="toc"><ul><li><details><summary><a href="/a">a</a></summary><ul><li><a href="/a/b">b</a></li><li><a href="/a/one%2Emd">one.md</a></li></ul></details></li><li><a href="/top%2Emd">top.md</a></li></ul></nav>"#);
+}
+
+#[test_case(true ; "with readme")]
+#[test_case(false ; "without readme")]
+#[test]
+fn test_toc_readme(readme: bool) {
+	let (temp_dir, config) = toc_tree();
+	if readme {
+		fs::write(temp_dir.path().join("a/README.md"), "# About A\n\ntext")
+			.expect("Should write README.md");
+	}
+
+	let rendered = crate::parser::directory::render_toc(
+		"A",
+		&temp_dir.path().join("a"),
+		&config,
+		&Default::default(),
+	)
+	.unwrap();
+	let about = rendered.body.find("About A");
+	let toc = rendered
+		.body
+		.find("one%2Emd")
+		.expect("TOC should be listed");
+	assert_eq!(about.is_some(), readme);
+	assert!(about.is_none_or(|about| about < toc), "{}", rendered.body);
+}
+
+#[test_case(&[], None ; "no candidates")]
+#[test_case(&["index.html", "index.md", "README.md"], Some("index.md") ; "first existing wins")]
+#[test_case(&["README.md", "index.md"], Some("README.md") ; "order is configurable")]
+#[test_case(&["a"], None ; "directories are not index files")]
+#[test]
+fn test_directory_index_file(candidates: &[&str], expected: Option<&str>) {
+	let temp_dir = create_test_dir();
+	fs::create_dir(temp_dir.path().join("a")).expect("Should create a");
+	fs::write(temp_dir.path().join("index.md"), "# Index").expect("Should write index.md");
+	fs::write(temp_dir.path().join("README.md"), "# Readme").expect("Should write README.md");
+
+	let mut config = Cofg::default();
+	config.directory.index = candidates.iter().map(|s| s.to_string()).collect();
+
+	let index = crate::parser::directory::index_file(temp_dir.path(), &config);
+	assert_eq!(index, expected.map(|name| temp_dir.path().join(name)));
+}
+
+#[test_case("Hello World", "hello-world" ; "Ascii words")]
+#[test_case("中文 標題", "中文-標題" ; "CJK")]
+#[test_case("What's `new`? (v2.0)", "whats-new-v20" ; "Punctuation dropped")]
+#[test_case("snake_case-id", "snake_case-id" ; "Underscore and hyphen kept")]
+#[test]
+fn test_heading_slugify(text: &str, expected: &str) {
+	assert_eq!(crate::parser::heading::slugify(text), expected);
+}
+
+#[test]
+fn test_heading_anchors_and_outline() {
+	use crate::parser::heading::HeadingAnchors;
+	use markdown_ppp::ast_transform::Transformer as _;
+
+	let mut config = Cofg::default();
+	config.markdown.heading.anchors = true;
+	config.markdown.heading.permalink = true;
+	let ast = parse("# 介紹\n\n## Setup\n\n### Deep\n\n## Setup\n\n# End".to_string()).unwrap();
+	let mut anchors = HeadingAnchors::new(&config.markdown.heading);
+	let ast = anchors.transform_document(ast);
+
+	let outline = anchors.outline();
+	let ids: Vec<(Option<&str>, Vec<Option<&str>>)> = outline
+		.iter()
+		.map(|e| {
+			(
+				e.id.as_deref(),
+				e.children.iter().map(|c| c.id.as_deref()).collect(),
+			)
+		})
+		.collect();
+	assert_eq!(
+		ids,
+		[
+			(Some("介紹"), vec![Some("setup"), Some("setup-1")]),
+			(Some("end"), vec![])
+		]
+	);
+	assert_eq!(
+		outline[0].children[0].children[0].id.as_deref(),
+		Some("deep")
+	);
+
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+	insta::assert_snapshot!(html, @r##"<h1><a class="anchor" id="介紹" href="#介紹" aria-hidden="true">#</a>介紹</h1><h2><a class="anchor" id="setup" href="#setup" aria-hidden="true">#</a>Setup</h2><h3><a class="anchor" id="deep" href="#deep" aria-hidden="true">#</a>Deep</h3><h2><a class="anchor" id="setup-1" href="#setup-1" aria-hidden="true">#</a>Setup</h2><h1><a class="anchor" id="end" href="#end" aria-hidden="true">#</a>End</h1>"##);
+}
+
+#[test]
+fn test_heading_outline_without_anchors() {
+	use crate::parser::heading::HeadingAnchors;
+	use markdown_ppp::ast_transform::Transformer as _;
+
+	let config = Cofg::default();
+	let ast = parse("# Intro\n\n## Setup".to_string()).unwrap();
+	let mut anchors = HeadingAnchors::new(&config.markdown.heading);
+	let ast = anchors.transform_document(ast);
+
+	let outline = serde_json::to_value(anchors.outline()).unwrap();
+	assert_eq!(
+		outline,
+		serde_json::json!([{"level": 1, "text": "Intro", "children": [
+			{"level": 2, "text": "Setup", "children": []}
+		]}])
+	);
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+	insta::assert_snapshot!(html, @"<h1>Intro</h1><h2>Setup</h2>");
+}
+
+#[cfg(feature = "highlight")]
+#[test_case("class", "rust" ; "Class mode")]
+#[test_case("inline", "rust" ; "Inline mode")]
+#[test_case("class", "no-such-lang" ; "Unknown language")]
+#[actix_web::test]
+async fn test_highlight_code_block(mode: &str, lang: &str) {
+	use crate::cofg::config::HighlightMode;
+	use crate::parser::highlight::Highlight;
+	use markdown_ppp::ast_transform::Transform as _;
+
+	let mut config = Cofg::default();
+	config.markdown.highlight.enable = true;
+	config.markdown.highlight.mode = match mode {
+		"class" => HighlightMode::Class,
+		_ => HighlightMode::Inline,
+	};
+	let ast = parse(format!("```{lang}\nfn main() {{}}\n```")).unwrap();
+	let ast = ast.transform_with(Highlight(&config.markdown.highlight));
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+
+	match (mode, lang) {
+		("class", "rust") => {
+			assert!(html.starts_with(r#"<pre class="hl-code"><code class="language-rust">"#));
+			assert!(
+				html.contains(r#"<span class="hl-storage hl-type hl-function hl-rust">fn</span>"#)
+			);
+		}
+		("inline", "rust") => {
+			assert!(html.starts_with("<pre style=\"background-color:"));
+			assert!(!html.contains("class="));
+		}
+		_ => assert_eq!(html, "<pre><code>fn main() {}</code></pre>"),
+	}
+}
+
+#[cfg(feature = "highlight")]
+#[actix_web::test]
+async fn test_highlight_theme_css() {
+	let mut config = Cofg::default().markdown.highlight;
+	let css = crate::parser::highlight::theme_css(&config).expect("default theme exists");
+	assert!(css.contains(".hl-"));
+
+	config.theme = "no such theme".to_string();
+	assert!(crate::parser::highlight::theme_css(&config).is_none());
+}
+
+#[test_case("$a_1 + b_1$", &[(false, "a_1 + b_1")] ; "Inline math")]
+#[test_case("$$\n\\{x\\} \\\\ y\n$$", &[(true, "\n\\{x\\} \\\\ y\n")] ; "Display math keeps escapes")]
+#[test_case("price $5 and $10", &[] ; "Currency is not math")]
+#[test_case("\\$x$", &[] ; "Escaped dollar")]
+#[test_case("`$x$` and ``a `$y$` b``", &[] ; "Code spans skipped")]
+#[test_case("```\n$x$\n```\n$y$", &[(false, "y")] ; "Fenced code skipped")]
+#[test_case("$a\n\nb$", &[] ; "No blank line inside")]
+#[actix_web::test]
+async fn test_math_extract(md: &str, expected: &[(bool, &str)]) {
+	let (out, spans) = extract(md);
+	let got: Vec<(bool, &str)> = spans.iter().map(|s| (s.display, s.tex.as_str())).collect();
+	assert_eq!(got, expected);
+	if expected.is_empty() {
+		assert_eq!(out, md);
+	}
+}
+
+#[actix_web::test]
+async fn test_math_render_with_fallback() {
+	let (md, spans) = extract("**bold** $x < 1$ and $\\frac{1}{$");
+	let ast = crate::parser::math::render(parse(md).unwrap(), &spans);
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+	let html = crate::parser::math::restore(html, &spans);
+	insta::assert_snapshot!(html, @r#"<p><b>bold</b> <math xmlns="http://www.w3.org/1998/Math/MathML" display="inline"><mi>x</mi><mo>&lt;</mo><mn>1</mn></math> and $\frac{1}{$</p>"#);
+}
+
+#[test_case("en", None, "WARNING", "Warning" ; "English preset")]
+#[test_case("zh-TW", None, "NOTE", "備註" ; "Traditional Chinese preset")]
+#[test_case("zh-CN", None, "CAUTION", "注意" ; "Simplified Chinese preset")]
+#[test_case("zh-TW", Some(("tip", "小技巧")), "TIP", "小技巧" ; "Override wins")]
+#[test_case("en", Some(("todo", "To do")), "TODO", "To do" ; "Custom type override")]
+#[test_case("en", None, "TODO", "TODO" ; "Custom type falls back to name")]
+#[actix_web::test]
+async fn test_alert_title(
+	locale: &str,
+	r#override: Option<(&str, &str)>,
+	marker: &str,
+	expected: &str,
+) {
+	let mut config = Cofg::default().markdown.alerts;
+	config.locale = locale.to_string();
+	config.titles = r#override.map(|(k, v)| [(k.to_string(), v.to_string())].into());
+
+	let ast = parse(format!("> [!{marker}]\n> body")).unwrap();
+	let Block::GitHubAlert(alert) = &ast.blocks[0] else {
+		panic!("not parsed as alert: {ast:?}");
+	};
+	assert_eq!(
+		crate::parser::alert::title(&config, &alert.alert_type),
+		expected
+	);
+}
+
+#[actix_web::test]
+async fn test_alert_render_nested() {
+	let mut config = Cofg::default().markdown.alerts;
+	config.locale = "zh-TW".to_string();
+	let ast = parse("- item\n\n  > [!WARNING]\n  > **careful**".to_string()).unwrap();
+	let ast = crate::parser::alert::render(ast, &config);
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+	assert!(html.contains(r#"<div class="markdown-alert markdown-alert-warning"><p class="markdown-alert-title"><svg class="octicon octicon-alert mr-2""#));
+	assert!(html.contains("</svg>警告</p><p><b>careful</b></p></div>"));
+}
+
+#[test_case("Page One", Some("/Page%20One%2Emd") ; "By name keeps url case")]
+#[test_case("page one.md", Some("/Page%20One%2Emd") ; "Case-insensitive with extension")]
+#[test_case("notes", Some("/notes%2Emd") ; "Shallowest name wins")]
+#[test_case("sub/notes", Some("/sub/notes%2Emd") ; "Relative path")]
+#[test_case("Page One#Getting Started", Some("/Page%20One%2Emd#getting-started") ; "Section anchor")]
+#[test_case("#Local", Some("#local") ; "Same-page section")]
+#[test_case("skip", None ; "Ignored directory")]
+#[test_case("readme", None ; "Non-markdown file")]
+#[test_case("Guide", Some("/guide%2Emarkdown") ; "Other markdown extension")]
+#[actix_web::test]
+async fn test_wiki_resolve(target: &str, expected: Option<&str>) {
+	let temp_dir = create_test_dir();
+	fs::create_dir_all(temp_dir.path().join("sub")).expect("Should create sub");
+	fs::create_dir_all(temp_dir.path().join("node_modules")).expect("Should create ig dir");
+	fs::write(temp_dir.path().join("Page One.md"), "").expect("Should write page");
+	fs::write(temp_dir.path().join("notes.md"), "").expect("Should write notes");
+	fs::write(temp_dir.path().join("sub/notes.md"), "").expect("Should write sub notes");
+	fs::write(temp_dir.path().join("node_modules/skip.md"), "").expect("Should write skip");
+	fs::write(temp_dir.path().join("readme.txt"), "").expect("Should write readme");
+	fs::write(temp_dir.path().join("guide.markdown"), "").expect("Should write guide");
+
+	let mut config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	config.toc.ext.insert("markdown".to_string());
+	let index = crate::parser::wiki::WikiIndex::build(&config).unwrap();
+
+	assert_eq!(index.resolve(target, &config).as_deref(), expected);
+}
+
+#[actix_web::test]
+async fn test_wiki_render() {
+	let temp_dir = create_test_dir();
+	fs::write(temp_dir.path().join("Home.md"), "").expect("Should write page");
+	let config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+
+	let ast = parse("See [[home|the *home* page]] and [[Missing]].".to_string()).unwrap();
+	let ast = crate::parser::wiki::render(ast, &config);
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+	insta::assert_snapshot!(html, @r#"<p>See <a href="/Home%2Emd">the <em>home</em> page</a> and <span class="wikilink-missing" title="Missing">Missing</span>.</p>"#);
+}
+
+#[test_case("shared/b.md", 8, "B text", "<!--" ; "Relative to the including file")]
+#[test_case("/shared/a.md", 8, "<b>A</b></p><p>B text", "<!--" ; "Absolute from public_path and nested")]
+#[test_case("/shared/a.md", 1, "<b>A</b></p><!-- include: b.md -->", "B text" ; "Depth limit keeps directive")]
+#[test_case("../secret.md", 8, "<!-- include: ../secret.md -->", "Secret" ; "Traversal outside public_path")]
+#[test_case("cycle.md", 8, "Cycle</p><!-- include: page.md -->", "Page" ; "Cycle back to the page")]
+#[test_case("missing.md", 8, "<!-- include: missing.md -->", "<p>" ; "Missing file")]
+#[test_case("shared/private.md", 8, "<!-- include: shared/private.md -->", "Private" ; "Ignored with toc.ig_deny")]
+#[actix_web::test]
+async fn test_include_expand(target: &str, max_depth: usize, expected: &str, absent: &str) {
+	let temp_dir = create_test_dir();
+	let public = temp_dir.path().join("public");
+	fs::create_dir_all(public.join("shared")).expect("Should create shared");
+	fs::write(temp_dir.path().join("secret.md"), "Secret").expect("Should write secret");
+	fs::write(
+		public.join("shared/a.md"),
+		"Shared **A**\n\n<!-- include: b.md -->",
+	)
+	.expect("Should write a");
+	fs::write(public.join("shared/b.md"), "B text").expect("Should write b");
+	fs::write(public.join("shared/private.md"), "Private").expect("Should write private");
+	fs::write(public.join(".gitignore"), "private.md").expect("Should write .gitignore");
+	fs::write(
+		public.join("cycle.md"),
+		"Cycle\n\n<!-- include: page.md -->",
+	)
+	.expect("Should write cycle");
+	let page = public.join("page.md");
+	fs::write(&page, "Page").expect("Should write page");
+
+	let mut config = Cofg {
+		public_path: public.to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	config.markdown.include.max_depth = max_depth;
+	config.toc.ig_deny = true;
+
+	// Only look at what replaced the directive.
+	let ast = parse(format!("<!-- include: {target} -->\n")).unwrap();
+	let ast = crate::parser::include::expand(ast, &config, Some(&page), &mut Vec::new());
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default(),
+	);
+	let html = html.split_whitespace().collect::<Vec<_>>().join(" ");
+
+	assert!(html.contains(expected), "{html}");
+	assert!(!html.contains(absent), "{html}");
+}
+
+#[test_case(HtmlPolicy::Allow, &["<script>alert(1)</script>", r#"<div onclick="x">"#] ; "Allow passes raw html")]
+#[test_case(HtmlPolicy::Safe, &["<div>hi</div>", r#"<svg class="octicon octicon-info mr-2" viewBox="0 0 16 16""#, r##"<a class="anchor" id="title" href="#title" aria-hidden="true">"##, "<mo>&lt;</mo>"] ; "Safe strips scripts and keeps own markup")]
+#[test_case(HtmlPolicy::Escape, &["&lt;script&gt;alert(1)&lt;/script&gt;", "&lt;div onclick=&quot;x&quot;&gt;", r#"<div class="markdown-alert markdown-alert-note">"#] ; "Escape shows raw html as text")]
+#[actix_web::test]
+async fn test_sanitize_policy(policy: HtmlPolicy, expected: &[&str]) {
+	let mut config = Cofg::default();
+	config.markdown.sanitize.policy = policy;
+	config.markdown.math.enable = true;
+	config.markdown.heading.anchors = true;
+	config.markdown.heading.permalink = true;
+	let md = "# Title\n\n> [!NOTE]\n> $x<1$\n\n<script>alert(1)</script>\n\n<div onclick=\"x\">hi</div>\n";
+
+	let html = markdown_body(md.to_string(), &config, &PageContext::default())
+		.unwrap()
+		.body;
+
+	for e in expected {
+		assert!(html.contains(e), "missing {e}:\n{html}");
+	}
+	if policy != HtmlPolicy::Allow {
+		assert!(!html.contains("<script>alert"), "{html}");
+		assert!(!html.contains("onclick=\""), "{html}");
+	}
+}
+
+#[test]
+fn test_sanitize_safe_strips_document_ids() {
+	let mut config = Cofg::default();
+	config.markdown.sanitize.policy = HtmlPolicy::Safe;
+	config.markdown.heading.anchors = true;
+	let md = "# Title\n\n<div id=\"L1\" class=\"note\">hi</div>\n";
+
+	let html = markdown_body(md.to_string(), &config, &PageContext::default())
+		.unwrap()
+		.body;
+
+	assert!(html.contains(r#"<div class="note">hi</div>"#), "{html}");
+	assert!(!html.contains(r#"id="L1""#), "{html}");
+	assert!(html.contains(r#"<a id="title"></a>"#), "{html}");
+}
+
+#[test_case("---\ntitle: Hello\ntags: [a, b]\n---\n# Body\n", r#"{"tags":["a","b"],"title":"Hello"}"#, "# Body\n" ; "Mapping is front matter")]
+#[test_case("---\r\ndraft: true\r\n...\r\nBody", r#"{"draft":true}"#, "Body" ; "CRLF and dots terminator")]
+#[test_case("---\nJust prose\n---\nBody", "{}", "---\nJust prose\n---\nBody" ; "Prose between rules is kept")]
+#[test_case("---\ntitle: unclosed\n", "{}", "---\ntitle: unclosed\n" ; "Unclosed block is kept")]
+#[test_case("# No front matter", "{}", "# No front matter" ; "No front matter")]
+#[test]
+fn test_front_matter_extract(md: &str, values: &str, body: &str) {
+	let (fm, rest) = crate::parser::front_matter::extract(md.to_string());
+	assert_eq!(serde_json::Value::Object(fm).to_string(), values);
+	assert_eq!(rest, body);
+}
+
+#[test]
+fn test_markdown_parser_config() {
+	let mut config = Cofg::default();
+	config.markdown.parser.strikethrough = false;
+	config.markdown.heading.anchors = false;
+
+	let html = markdown_body(
+		"# T\n\n~~gone~~ *kept*".to_string(),
+		&config,
+		&PageContext::default(),
+	)
+	.unwrap()
+	.body;
+
+	assert!(html.contains("<p>~~gone~~ <em>kept</em></p>"), "{html}");
+}
+
+#[test]
+fn test_front_matter_overrides_markdown_config() {
+	let mut config = Cofg::default();
+	config.markdown.heading.anchors = false;
+	config.markdown.sanitize.policy = HtmlPolicy::Escape;
+	let md = "---\ntitle: Page\nmarkdown:\n  parser:\n    strikethrough: false\n  sanitize:\n    policy: allow\n---\n~~x~~ <b>y</b>\n\n<i>raw</i>\n";
+
+	let html = markdown_body(md.to_string(), &config, &PageContext::default())
+		.unwrap()
+		.body;
+
+	assert!(html.contains("~~x~~"), "page override applies: {html}");
+	assert!(
+		html.contains("&lt;i&gt;raw&lt;/i&gt;"),
+		"sanitize is site-only: {html}"
+	);
+	assert!(
+		!html.contains("title: Page"),
+		"front matter is not rendered: {html}"
+	);
+}
+
+#[test]
+fn test_front_matter_site_only_keys() {
+	let mut config = Cofg::default();
+	config.markdown.include.enable = false;
+	config.markdown.heading.permalink_symbol = "#".to_string();
+	let page = serde_json::json!({
+		"heading": { "permalink": true, "permalink_symbol": "<script>x</script>" },
+		"alerts": { "titles": { "note": "<script>y</script>" } },
+		"links": { "external_class": "\"><script>", "new_tab": true },
+		"include": { "enable": true, "max_depth": 99 },
+		"images": { "lazy": false },
+	});
+
+	let merged = crate::parser::front_matter::overlay(&config.markdown, &page).unwrap();
+
+	assert!(merged.heading.permalink, "toggles apply");
+	assert!(merged.links.new_tab, "toggles apply");
+	assert_eq!(merged.heading.permalink_symbol, "#");
+	assert_eq!(merged.alerts.titles, config.markdown.alerts.titles);
+	assert_eq!(
+		merged.links.external_class,
+		config.markdown.links.external_class
+	);
+	assert_eq!(merged.include, config.markdown.include);
+	assert_eq!(merged.images, config.markdown.images);
+}
+
+#[actix_web::test]
+async fn test_links_render() {
+	let temp_dir = create_test_dir();
+	fs::create_dir_all(temp_dir.path().join("docs")).expect("Should create docs");
+	fs::write(temp_dir.path().join("docs/a b.md"), "").expect("Should write page");
+	let mut config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	config.markdown.links.new_tab = true;
+	config.markdown.links.external_class = "external".to_string();
+	config.markdown.links.check_md = true;
+	config.markdown.links.rewrite = Some(
+		[(
+			"https://github.com/org/repo/blob/main/".to_string(),
+			"/".to_string(),
+		)]
+		.into(),
+	);
+	let md = "[ok](a%20b.md#x) [abs](/docs/a%20b.md) [gone](missing.md) [up](../../etc/x.md) \
+		[ext](https://example.com \"t\") [gh](https://github.com/org/repo/blob/main/docs/a%20b.md) \
+		[mail](mailto:a@b.c) <https://x.org> [ref ok][r1] [ref gone][r2] [ref ext][r3]\n\n\
+		[r1]: a%20b.md\n[r2]: gone.md \"t\"\n[r3]: https://github.com/org/repo/blob/main/docs/a%20b.md\n";
+
+	let ast = parse(md.to_string()).unwrap();
+	let page = temp_dir.path().join("docs/page.md");
+	let ast = crate::parser::links::render(ast, &config, Some(&page));
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default().with_width(1000),
+	);
+	insta::assert_snapshot!(html, @r#"<p><a href="a%20b.md#x">ok</a> <a href="/docs/a%20b.md">abs</a> <a href="missing.md" class="broken-link">gone</a> <a href="../../etc/x.md" class="broken-link">up</a> <a href="https://example.com" title="t" class="external" target="_blank" rel="noopener noreferrer">ext</a> <a href="/docs/a%20b.md">gh</a> <a href="mailto:a@b.c">mail</a> <a href="https://x.org" class="external" target="_blank" rel="noopener noreferrer">https://x.org</a> <a href="a%20b.md">ref ok</a> <a href="gone.md" title="t" class="broken-link">ref gone</a> <a href="/docs/a%20b.md">ref ext</a></p>"#);
+}
+
+#[cfg(feature = "images")]
+#[actix_web::test]
+async fn test_images_render() {
+	let temp_dir = create_test_dir();
+	image::RgbImage::new(1000, 20)
+		.save(temp_dir.path().join("shot.png"))
+		.expect("Should write png");
+	let mut config = Cofg {
+		public_path: temp_dir.path().to_string_lossy().to_string(),
+		..Cofg::default()
+	};
+	config.markdown.images.variants.enable = true;
+	let md = "![a \"shot\"](shot.png) ![remote](https://example.com/x.png \"t\") ![gone](gone.png)";
+
+	let ast = parse(md.to_string()).unwrap();
+	let ast = crate::parser::images::render(ast, &config, None);
+	let html = markdown_ppp::html_printer::render_html(
+		&ast,
+		markdown_ppp::html_printer::config::Config::default().with_width(1000),
+	);
+	insta::assert_snapshot!(html, @r#"<p><img src="shot.png" alt="a &quot;shot&quot;" width="1000" height="20" srcset="shot.png?w&#x3D;480 480w, shot.png?w&#x3D;960 960w, shot.png 1000w"> <img src="https://example.com/x.png" alt="remote" title="t"></img> <img src="gone.png" alt="gone"></img></p>"#);
+}
+
+#[cfg(feature = "images")]
+#[test_case(480, Some((480, 10)) ; "Configured width")]
+#[test_case(500, None ; "Width not configured")]
+#[test_case(1440, None ; "Not smaller than original")]
+#[test]
+fn test_image_variant(width: u32, expected: Option<(u32, u32)>) {
+	let temp_dir = create_test_dir();
+	let src = temp_dir.path().join("shot.png");
+	image::RgbImage::new(1000, 20)
+		.save(&src)
+		.expect("Should write png");
+	let mut config = Cofg::default();
+	config.markdown.images.variants.enable = true;
+	config.markdown.images.variants.cache_dir =
+		Some(temp_dir.path().join("cache").to_string_lossy().into_owned());
+
+	let variant = crate::parser::images::variant(&config, &src, width).unwrap();
+
+	assert_eq!(
+		variant.and_then(|p| crate::parser::images::dimensions(&p)),
+		expected
+	);
+}
+
+#[cfg(all(feature = "images", unix))]
+#[test_case(0o700, true ; "Private")]
+#[test_case(0o777, false ; "World writable")]
+#[test]
+fn test_image_cache_dir_private(mode: u32, ok: bool) {
+	use std::os::unix::fs::PermissionsExt;
+
+	let temp_dir = create_test_dir();
+	let src = temp_dir.path().join("shot.png");
+	image::RgbImage::new(1000, 20)
+		.save(&src)
+		.expect("Should write png");
+	let cache = temp_dir.path().join("cache");
+	fs::create_dir(&cache).expect("Should create cache dir");
+	fs::set_permissions(&cache, fs::Permissions::from_mode(mode)).expect("Should chmod");
+	let mut config = Cofg::default();
+	config.markdown.images.variants.enable = true;
+	config.markdown.images.variants.cache_dir = Some(cache.to_string_lossy().into_owned());
+
+	assert_eq!(
+		crate::parser::images::variant(&config, &src, 480).is_ok(),
+		ok
+	);
+}
+
+#[test_case("a.md", true ; "Default md")]
+#[test_case("a.MARKDOWN", true ; "Extension case insensitive")]
+#[test_case("a.mdown", true ; "Mdown alias")]
+#[test_case("a.txt", false ; "Unmapped extension")]
+#[test_case("a.rst", false ; "Unknown renderer name")]
+#[test_case("README", false ; "No extension")]
+#[test]
+fn test_renderer_for_path(path: &str, expected: bool) {
+	let mut config = Cofg::default();
+	config.renderers.insert("rst".into(), "restructured".into());
+
+	let renderer = crate::parser::renderer::for_path(std::path::Path::new(path), &config);
+
+	assert_eq!(renderer.is_some(), expected);
+}
+
+#[test]
+fn test_markdown_renderer_alias() {
+	let temp_dir = create_test_dir();
+	let path = temp_dir.path().join("notes.mdown");
+	fs::write(&path, "# Notes\n\nbody").expect("Should write mdown");
+	let config = Cofg::default();
+
+	let renderer = crate::parser::renderer::for_path(&path, &config).expect("mdown renderer");
+	let rendered = renderer
+		.render(&path, &config, &crate::parser::PageContext::default())
+		.unwrap();
+
+	assert!(rendered.body.contains("<h1"), "{}", rendered.body);
+	assert!(rendered.values.contains_key("outline"));
+}
+
+#[test_case("name,qty\na,1\n", TableHeader::Auto, true ; "Auto text header")]
+#[test_case("1,2\n3,4\n", TableHeader::Auto, false ; "Auto numeric first row")]
+#[test_case("a,a\nb,c\n", TableHeader::Auto, false ; "Auto duplicate cells")]
+#[test_case("a,\nb,c\n", TableHeader::Auto, false ; "Auto empty cell")]
+#[test_case("1,2\n3,4\n", TableHeader::Always, true ; "Always")]
+#[test_case("name,qty\na,1\n", TableHeader::Never, false ; "Never")]
+#[test]
+fn test_table_header(csv: &str, header: TableHeader, expected: bool) {
+	let mut config = Cofg::default();
+	config.table.header = header;
+
+	let rendered = crate::parser::table::render(csv.as_bytes(), b',', &config.table, 1).unwrap();
+
+	assert_eq!(
+		rendered.body.contains("<thead>"),
+		expected,
+		"{}",
+		rendered.body
+	);
+}
+
+#[test]
+fn test_table_render() {
+	let config = Cofg::default();
+	let tsv = "name\tnote\nalpha\t<b>&</b>\nbeta\n";
+
+	let rendered = crate::parser::table::render(tsv.as_bytes(), b'\t', &config.table, 1).unwrap();
+
+	insta::assert_snapshot!(rendered.body, @r#"
+	<table class="csv-table sortable">
+	<thead><tr><th>name</th><th>note</th></tr>
+	</thead>
+	<tbody>
+	<tr><td>alpha</td><td>&lt;b&gt;&amp;&lt;/b&gt;</td></tr>
+	<tr><td>beta</td></tr>
+	</tbody>
+	</table>
+	<nav class="table-pager">rows 1–2 of 2 (page 1 / 1) · <a href="?raw">raw</a></nav>
+	"#);
+}
+
+#[test_case(1, "rows 1–2 of 5 (page 1 / 3)", &["r1", "r2"] ; "First page")]
+#[test_case(3, "rows 5–5 of 5 (page 3 / 3)", &["r5"] ; "Last page")]
+#[test_case(9, "0 of 5 rows (page 9 / 3)", &[] ; "Past the end")]
+#[test_case(usize::MAX, "0 of 5 rows (page 18446744073709551615 / 3)", &[] ; "Huge page number")]
+#[test]
+fn test_table_pagination(page: usize, info: &str, expected: &[&str]) {
+	let mut config = Cofg::default();
+	config.table.page_size = 2;
+	let csv = "id\nr1\nr2\nr3\nr4\nr5\n";
+
+	let rendered = crate::parser::table::render(csv.as_bytes(), b',', &config.table, page).unwrap();
+
+	assert!(rendered.body.contains(info), "{}", rendered.body);
+	for id in ["r1", "r2", "r3", "r4", "r5"] {
+		assert_eq!(
+			rendered.body.contains(&format!("<td>{id}</td>")),
+			expected.contains(&id),
+			"{id}"
+		);
+	}
+	assert_eq!(rendered.values["table"]["rows"], 5);
+}
+
+const NOTEBOOK: &str = r##"{
+ "metadata": {"kernelspec": {"language": "python"}},
+ "nbformat": 4,
+ "cells": [
+  {"cell_type": "markdown", "metadata": {}, "source": ["# Report\n", "\n", "Some *text*"]},
+  {"cell_type": "code", "metadata": {}, "execution_count": 1, "source": "print('a < b')", "outputs": [
+   {"output_type": "stream", "name": "stdout", "text": ["a < b\n"]},
+   {"output_type": "execute_result", "execution_count": 1, "metadata": {},
+    "data": {"text/html": "<b onclick=\"x()\">bold</b><script>x()</script>", "text/plain": "plain"}},
+   {"output_type": "display_data", "metadata": {}, "data": {"image/png": "iVBORw0K\nGgo=\n", "text/plain": "<Figure>"}},
+   {"output_type": "display_data", "metadata": {}, "data": {"image/svg+xml": ["<svg><rect/></svg>"]}},
+   {"output_type": "error", "ename": "ValueError", "evalue": "bad", "traceback": ["\u001b[0;31mValueError\u001b[0m: bad"]}
+  ]},
+  {"cell_type": "raw", "metadata": {}, "source": "\\LaTeX only"},
+  {"cell_type": "code", "metadata": {}, "execution_count": 2, "source": "x = 1", "outputs": []}
+ ]
+}"##;
+
+#[test]
+fn test_notebook_render() {
+	let mut config = Cofg::default();
+	config.markdown.heading.permalink = false;
+
+	let rendered =
+		crate::parser::notebook::render(NOTEBOOK, &config, &crate::parser::PageContext::default())
+			.unwrap();
+
+	insta::assert_snapshot!(rendered.body, @r#"<h1>Report</h1><p>Some <em>text</em></p><pre><code>print(&apos;a &lt; b&apos;)</code></pre><pre class="nb-output nb-stdout">a &lt; b</pre><div class="nb-output"><b onclick="x()">bold</b><script>x()</script></div><img class="nb-output" alt="output" src="data:image/png;base64,iVBORw0KGgo&#x3D;"><img class="nb-output" alt="output" src="data:image/svg+xml,%3Csvg%3E%3Crect%2F%3E%3C%2Fsvg%3E"><pre class="nb-output nb-error">ValueError: bad</pre><pre><code>x = 1</code></pre>"#);
+	assert_eq!(rendered.values["notebook"]["cells"], 4);
+	assert!(rendered.values.contains_key("outline"));
+}
+
+#[test]
+fn test_notebook_unknown_types_skipped() {
+	let src = r#"{"cells": [
+  {"cell_type": "heading", "level": 1, "source": "Old"},
+  {"cell_type": "markdown", "source": "kept"},
+  {"cell_type": "code", "source": "x", "outputs": [
+   {"output_type": "pyout", "text": "old"},
+   {"output_type": "stream", "name": "stdout", "text": "out"}
+  ]}
+ ]}"#;
+
+	let rendered = crate::parser::notebook::render(
+		src,
+		&Cofg::default(),
+		&crate::parser::PageContext::default(),
+	)
+	.unwrap();
+
+	insta::assert_snapshot!(rendered.body, @r#"<p>kept</p><pre><code>x</code></pre><pre class="nb-output nb-stdout">out</pre>"#);
+}
+
+#[test]
+fn test_notebook_placeholder_lookalikes_kept() {
+	let src = r#"{"cells": [
+  {"cell_type": "markdown", "source": "\ue0020\ue003 and \ue002\u0030\ue003"},
+  {"cell_type": "code", "source": "x", "outputs": [
+   {"output_type": "stream", "name": "stdout", "text": "out"}
+  ]}
+ ]}"#;
+
+	let rendered = crate::parser::notebook::render(
+		src,
+		&Cofg::default(),
+		&crate::parser::PageContext::default(),
+	)
+	.unwrap();
+
+	assert_eq!(
+		rendered.body.matches("nb-stdout").count(),
+		1,
+		"{}",
+		rendered.body
+	);
+	assert!(
+		rendered
+			.body
+			.contains("<p>\u{E002}0\u{E003} and \u{E002}0\u{E003}</p>"),
+		"{}",
+		rendered.body
+	);
+}
+
+#[test_case(HtmlPolicy::Allow, "<b onclick=\"x()\">bold</b><script>", "<pre class=\"nb-output nb-result\">plain" ; "Allow")]
+#[test_case(HtmlPolicy::Safe, "<b>bold</b></div>", "<script>" ; "Safe")]
+#[test_case(HtmlPolicy::Escape, "<pre class=\"nb-output nb-result\">plain</pre>", "<b" ; "Escape")]
+#[test]
+fn test_notebook_output_policy(policy: HtmlPolicy, present: &str, absent: &str) {
+	let mut config = Cofg::default();
+	config.markdown.sanitize.policy = policy;
+
+	let rendered =
+		crate::parser::notebook::render(NOTEBOOK, &config, &crate::parser::PageContext::default())
+			.unwrap();
+
+	assert!(rendered.body.contains(present), "{}", rendered.body);
+	assert!(!rendered.body.contains(absent), "{}", rendered.body);
+}
+
+#[test]
+fn test_source_render_plain() {
+	let mut config = Cofg::default();
+	#[cfg(feature = "highlight")]
+	{
+		config.markdown.highlight.enable = false;
+	}
+	config.source.max_lines = 2;
+
+	let rendered =
+		crate::parser::source::render(&b"a: <1>\n\nc: 3\n"[..], "yaml", &config).unwrap();
+
+	insta::assert_snapshot!(rendered.body, @r##"
+	<p class="source-info">first 2 lines shown · <a href="?raw">raw</a></p>
+	<pre class="hl-code source"><code><span class="line" id="L1"><a class="ln" href="#L1" data-line="1"></a>a: &lt;1&gt;</span><span class="line" id="L2"><a class="ln" href="#L2" data-line="2"></a></span></code></pre>
+	"##);
+	assert_eq!(rendered.values["source"]["shown"], 2);
+	assert_eq!(rendered.values["source"]["truncated"], true);
+}
+
+#[test]
+fn test_source_render_reads_prefix_only() {
+	let mut config = Cofg::default();
+	config.source.max_lines = 3;
+	// Endless input: rendering must stop after `max_lines` instead of reading it all.
+	let input = std::io::Read::chain(&b"a\nb\nc\nd\n"[..], std::io::repeat(b'x'));
+
+	let rendered = crate::parser::source::render(input, "txt", &config).unwrap();
+
+	assert!(
+		rendered.body.contains("first 3 lines shown"),
+		"{}",
+		rendered.body
+	);
+	assert_eq!(rendered.values["source"]["shown"], 3);
+}
+
+#[test]
+fn test_source_render_binary() {
+	let rendered =
+		crate::parser::source::render(&b"\x89PNG\0\0"[..], "png", &Cofg::default()).unwrap();
+
+	assert!(rendered.body.contains("Binary file"), "{}", rendered.body);
+	assert!(!rendered.body.contains("<pre"));
+}
+
+#[cfg(feature = "highlight")]
+#[test_case("class" ; "Class mode")]
+#[test_case("inline" ; "Inline mode")]
+#[test]
+fn test_source_highlight_lines(mode: &str) {
+	let mut config = Cofg::default();
+	config.markdown.highlight.enable = true;
+	config.markdown.highlight.mode = serde_json::from_value(serde_json::json!(mode)).unwrap();
+	let code = "/* one\n   two */\nfn main() {}\n";
+
+	let rendered = crate::parser::source::render(code.as_bytes(), "rs", &config).unwrap();
+
+	let lines: Vec<&str> = rendered
+		.body
+		.split(r#"<span class="line""#)
+		.skip(1)
+		.collect();
+	assert_eq!(lines.len(), 3, "{}", rendered.body);
+	// Every line is self-contained even inside the block comment.
+	for line in &lines {
+		let line = line.trim_end_matches("</code></pre>\n");
+		assert_eq!(
+			line.matches("<span").count() + 1,
+			line.matches("</span>").count(),
+			"{line}"
+		);
+	}
+	assert!(lines[1].contains("two"));
+	assert_eq!(
+		rendered.values.contains_key("highlight_css"),
+		mode == "class"
+	);
+}
+
+fn listing(files: &[&str]) -> crate::parser::directory::Listing {
+	crate::parser::directory::Listing {
+		dirs: vec!["sub".into()],
+		files: files.iter().map(|f| f.to_string()).collect(),
+	}
+}
+
+#[test_case(&["a.png", "b.JPG", "c.txt"], None, DirectoryView::Gallery ; "Mostly images")]
+#[test_case(&["a.mp3", "b.webm", "c.txt"], None, DirectoryView::Media ; "Mostly media")]
+#[test_case(&["a.png", "b.mp4", "c.txt", "d.md"], None, DirectoryView::Toc ; "Mixed")]
+#[test_case(&[], None, DirectoryView::Toc ; "Only subdirectories")]
+#[test_case(&["a.png"], Some("toc"), DirectoryView::Toc ; "Query overrides auto")]
+#[test_case(&["a.png"], Some("list"), DirectoryView::List ; "Query selects index")]
+#[test_case(&["a.md"], Some("gallery"), DirectoryView::Gallery ; "Query forces gallery")]
+#[test_case(&["a.png"], Some("bogus"), DirectoryView::Gallery ; "Unknown query ignored")]
+#[test]
+fn test_directory_view_choose(files: &[&str], query: Option<&str>, expected: DirectoryView) {
+	let config = Cofg::default();
+
+	let view = crate::parser::directory::choose(&listing(files), &config, query);
+
+	assert_eq!(view, expected);
+}
+
+#[test]
+fn test_directory_gallery_render() {
+	let config = Cofg::default();
+
+	let rendered = crate::parser::media::render_directory(
+		"shots",
+		std::path::Path::new("shots"),
+		&listing(&["a b.png", "clip.mp4", "notes.md"]),
+		DirectoryView::Gallery,
+		&config,
+	);
+
+	#[cfg(feature = "images")]
+	insta::assert_snapshot!(rendered.body, @r#"
+	<h1>shots</h1>
+	<p class="directory-views"><a href="?view=toc">toc</a> · <a href="?view=list">list</a> · <a href="?view=gallery">gallery</a> · <a href="?view=media">media</a></p>
+	<ul class="directory-dirs"><li><a href="/shots/sub">sub/</a></li></ul>
+	<div class="gallery" style="--thumb: 240px"><a class="gallery-item" href="/shots/a%20b%2Epng"><img src="/shots/a%20b%2Epng?thumb" alt="a b.png" loading="lazy"><span>a b.png</span></a></div>
+	<ul class="directory-files"><li><a href="/shots/clip%2Emp4">clip.mp4</a></li><li><a href="/shots/notes%2Emd">notes.md</a></li></ul>
+	"#);
+	assert_eq!(rendered.values["directory"]["view"], "gallery");
+}
+
+#[test]
+fn test_directory_media_render() {
+	let config = Cofg::default();
+
+	let rendered = crate::parser::media::render_directory(
+		"clips",
+		std::path::Path::new(""),
+		&listing(&["a.mp3", "b.mp4", "c.png"]),
+		DirectoryView::Media,
+		&config,
+	);
+
+	insta::assert_snapshot!(rendered.body, @r#"
+	<h1>clips</h1>
+	<p class="directory-views"><a href="?view=toc">toc</a> · <a href="?view=list">list</a> · <a href="?view=gallery">gallery</a> · <a href="?view=media">media</a></p>
+	<ul class="directory-dirs"><li><a href="/sub">sub/</a></li></ul>
+	<figure class="media-item"><audio controls preload="metadata" src="/a%2Emp3?raw"></audio><figcaption><a href="/a%2Emp3?view=player">a.mp3</a></figcaption></figure>
+	<figure class="media-item"><video controls preload="metadata" src="/b%2Emp4?raw"></video><figcaption><a href="/b%2Emp4?view=player">b.mp4</a></figcaption></figure>
+	<ul class="directory-files"><li><a href="/c%2Epng">c.png</a></li></ul>
+	"#);
+}
+
+#[cfg(feature = "images")]
+#[test_case(1000, 20, Some((240, 5)) ; "Large image")]
+#[test_case(100, 20, None ; "Already small")]
+#[test]
+fn test_image_thumbnail(width: u32, height: u32, expected: Option<(u32, u32)>) {
+	let temp_dir = create_test_dir();
+	let src = temp_dir.path().join(format!("thumb-{width}.png"));
+	image::RgbImage::new(width, height)
+		.save(&src)
+		.expect("Should write png");
+
+	let mut config = Cofg::default();
+	config.markdown.images.variants.cache_dir =
+		Some(temp_dir.path().join("cache").to_string_lossy().into_owned());
+
+	let thumb = crate::parser::images::thumbnail(&config, &src).unwrap();
+
+	assert_eq!(
+		thumb.and_then(|p| crate::parser::images::dimensions(&p)),
+		expected
+	);
+}
+
+#[test_case("", None ; "Root has no parent")]
+#[test_case("docs", Some("/") ; "Parent is root")]
+#[test_case("docs/a b", Some("/docs") ; "Nested parent")]
+#[test]
+fn test_directory_index_parent(rel: &str, expected: Option<&str>) {
+	let temp_dir = create_test_dir();
+
+	let rendered = crate::parser::directory::render_index(
+		"t",
+		temp_dir.path(),
+		std::path::Path::new(rel),
+		&crate::parser::directory::Listing::default(),
+	);
+
+	let parent = rendered
+		.body
+		.split(r#"<p class="directory-parent"><a href=""#)
+		.nth(1)
+		.and_then(|rest| rest.split('"').next());
+	assert_eq!(parent, expected);
+}
+
+#[test]
+fn test_directory_index_render() {
+	let temp_dir = create_test_dir();
+	fs::create_dir(temp_dir.path().join("sub")).expect("Should create dir");
+	fs::write(temp_dir.path().join("data.bin"), vec![0u8; 2048]).expect("Should write file");
+	fs::write(temp_dir.path().join("notes.txt"), "hi").expect("Should write file");
+	fs::write(temp_dir.path().join(".hidden"), "x").expect("Should write file");
+	for name in ["sub", "data.bin", "notes.txt"] {
+		fs::File::open(temp_dir.path().join(name))
+			.and_then(|f| {
+				f.set_modified(
+					std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+				)
+			})
+			.expect("Should set mtime");
+	}
+	let config = Cofg::default();
+
+	let listing = crate::parser::directory::listing(temp_dir.path(), &config).unwrap();
+	let rendered = crate::parser::directory::render_index(
+		"files",
+		temp_dir.path(),
+		std::path::Path::new("files"),
+		&listing,
+	);
+
+	insta::assert_snapshot!(rendered.body, @r#"
+	<h1>files</h1>
+	<p class="directory-views"><a href="?view=toc">toc</a> · <a href="?view=list">list</a> · <a href="?view=gallery">gallery</a> · <a href="?view=media">media</a></p>
+	<p class="directory-parent"><a href="/">../</a></p>
+	<table class="directory-index sortable">
+	<thead><tr><th>Name</th><th>Size</th><th>Modified</th><th>Type</th></tr></thead>
+	<tbody>
+	<tr><td data-sort="0sub"><a href="/files/sub">sub/</a></td><td data-sort="-1">—</td><td data-sort="1700000000">Tue, 14 Nov 2023 22:13:20 GMT</td><td>directory</td></tr>
+	<tr><td data-sort="1data.bin"><a href="/files/data%2Ebin">data.bin</a></td><td data-sort="2048">2.0 KiB</td><td data-sort="1700000000">Tue, 14 Nov 2023 22:13:20 GMT</td><td>application/octet-stream</td></tr>
+	<tr><td data-sort="1notes.txt"><a href="/files/notes%2Etxt">notes.txt</a></td><td data-sort="2">2 B</td><td data-sort="1700000000">Tue, 14 Nov 2023 22:13:20 GMT</td><td>text/plain</td></tr>
+	</tbody>
+	</table>
+	"#);
+	assert_eq!(rendered.values["directory"]["sortable"], true);
+}
@@ -0,0 +1,100 @@
+//! Redirect / rewrite rule tests
+//!
+//! WHY: Validate `redirects` matching before it reaches `main_req`:
+//! - exact, prefix, glob and regex patterns with capture substitution
+//! - status choice, internal rewrites and query preservation
+//! - rule order and invalid rules
+
+use actix_web::http::StatusCode;
+use simple_test_case::test_case;
+
+use crate::cofg::config::{Cofg, RedirectMatch};
+use crate::redirect::{Action, resolve};
+use crate::test::support::cofg_with;
+
+fn redirect(status: u16, location: &str) -> Option<Action> {
+	Some(Action::Redirect {
+		status: StatusCode::from_u16(status).unwrap(),
+		location: location.to_string(),
+	})
+}
+
+fn rewritten(path: &str, query: &str) -> Option<Action> {
+	Some(Action::Rewrite {
+		path: path.to_string(),
+		query: query.to_string(),
+	})
+}
+
+#[test_case("/old", "", redirect(301, "/new") ; "exact")]
+#[test_case("/old/", "", None ; "exact needs the whole path")]
+#[test_case("/old", "a=1", redirect(301, "/new?a=1") ; "query preserved")]
+#[test_case("/docs", "", redirect(302, "/manual") ; "prefix itself")]
+#[test_case("/docs/a/b.md", "", redirect(302, "/manual/a/b.md") ; "prefix appends rest")]
+#[test_case("/docsx", "", None ; "prefix matches whole segments")]
+#[test_case("/img/2024/cat.png", "", redirect(308, "/media/cat-2024.png") ; "glob captures")]
+#[test_case("/posts/42-hello", "x=1", redirect(307, "/blog/42/hello") ; "regex named group and query dropped")]
+#[test_case("/latest/notes.md", "raw", rewritten("/releases/v4/notes.md", "raw") ; "rewrite")]
+#[test_case("/code", "a=1", rewritten("/src/main.rs", "view&a=1") ; "rewrite target query comes first")]
+#[test_case("/nothing", "", None ; "no match")]
+#[test]
+fn test_redirect_rules(path: &str, query: &str, expected: Option<Action>) {
+	let c = cofg_with(
+		r#"
+redirects:
+  - from: /old
+    to: /new
+  - from: /docs/
+    to: /manual/
+    match: prefix
+    status: 302
+  - from: /img/*/*.png
+    to: /media/$2-$1.png
+    match: glob
+    status: 308
+  - from: /posts/(\d+)-(?<slug>[a-z]+)
+    to: /blog/$1/${slug}
+    match: regex
+    status: 307
+    query: false
+  - from: /latest
+    to: releases/v4
+    match: prefix
+    rewrite: true
+  - from: /code
+    to: /src/main.rs?view
+    rewrite: true
+"#,
+	);
+	assert_eq!(resolve(path, query, &c), expected);
+}
+
+#[test]
+fn test_redirect_rule_defaults() {
+	let c = cofg_with("redirects:\n  - from: /a\n    to: /b\n");
+	let rule = &c.redirects[0];
+	assert_eq!(rule.kind, RedirectMatch::Exact);
+	assert_eq!(rule.status, 301);
+	assert!(rule.query);
+	assert!(!rule.rewrite);
+	assert!(Cofg::default().redirects.is_empty());
+}
+
+#[test]
+fn test_redirect_invalid_rules_skipped() {
+	let c = cofg_with(
+		r#"
+redirects:
+  - from: /a
+    to: /b
+    status: 200
+  - from: "/(unclosed"
+    to: /c
+    match: regex
+  - from: /a
+    to: /d
+"#,
+	);
+	assert_eq!(resolve("/a", "", &c), redirect(301, "/d"));
+	assert_eq!(resolve("/(unclosed", "", &c), None);
+}
@@ -7,8 +7,16 @@
 //! - TOC generation
 //! - Static file serving
 
-use crate::{request::main_req, test::support::assert_status_in};
+use crate::{
+	cofg::config::Cofg,
+	request::{main_req, spa_fallback},
+	test::{
+		config::create_test_dir,
+		support::{assert_status_in, cofg_with},
+	},
+};
 use actix_web::{App, http::StatusCode, test};
+use simple_test_case::test_case;
 
 // Note: server_error function is primarily exercised via request handlers that return errors.
 // A dedicated integration test (test_server_error_function in src/test/integration.rs) validates it directly.
@@ -216,3 +224,280 @@ async fn test_percent_encoded_spaces() {
 
 	assert_status_in(resp.status(), &[StatusCode::OK, StatusCode::NOT_FOUND]);
 }
+
+#[cfg(feature = "highlight")]
+#[actix_web::test]
+async fn test_highlight_css_falls_through_by_default() {
+	crate::test::support::init_test_setup();
+
+	let app = test::init_service(
+		App::new()
+			.service(crate::request::highlight_css)
+			.default_service(actix_web::web::to(|| async {
+				actix_web::HttpResponse::Ok().body("main_req")
+			})),
+	)
+	.await;
+
+	let req = test::TestRequest::get()
+		.uri("/__highlight.css")
+		.to_request();
+	let body = test::call_and_read_body(&app, req).await;
+
+	assert_eq!(body, "main_req");
+}
+
+#[test_case("/app/users/42", Some("app/index.html") ; "client route")]
+#[test_case("/app", Some("app/index.html") ; "prefix itself")]
+#[test_case("/apple", None ; "prefix matches whole segments")]
+#[test_case("/app/admin/x", Some("app/admin.html") ; "longest prefix wins")]
+#[test_case("/docs/x", None ; "missing fallback file")]
+#[test_case("/other", None ; "outside every prefix")]
+#[actix_web::test]
+async fn test_spa_fallback(path: &str, expected: Option<&str>) {
+	let temp_dir = create_test_dir();
+	std::fs::create_dir(temp_dir.path().join("app")).expect("Should create app");
+	std::fs::write(temp_dir.path().join("app/index.html"), "<div id=root>")
+		.expect("Should write index.html");
+	std::fs::write(temp_dir.path().join("app/admin.html"), "<div id=admin>")
+		.expect("Should write admin.html");
+
+	let config = Cofg {
+		spa: [
+			("/app/", "app/index.html"),
+			("/app/admin", "/app/admin.html"),
+			("/docs", "docs/index.html"),
+		]
+		.into_iter()
+		.map(|(k, v)| (k.to_string(), v.to_string()))
+		.collect(),
+		..Cofg::default()
+	};
+
+	assert_eq!(
+		spa_fallback(path, temp_dir.path(), &config),
+		expected.map(|file| temp_dir.path().join(file))
+	);
+}
+
+/// Built-in config with `yaml` layered on top, serving `dir`.
+fn cofg_for(dir: &std::path::Path, yaml: &str) -> Cofg {
+	let mut config = cofg_with(yaml).configure_default_extensions();
+	config.public_path = dir.to_string_lossy().into_owned();
+	config
+}
+
+#[test_case("/notes.md?view", true ; "bare view")]
+#[test_case("/notes.md?view=source", true ; "view source")]
+#[test_case("/notes.md?view=player", false ; "player on a non-media file")]
+#[test_case("/notes.md?view=gallery", false ; "directory view on a file")]
+#[actix_web::test]
+async fn test_view_source_only_when_asked(uri: &str, source: bool) {
+	let temp_dir = create_test_dir();
+	std::fs::write(temp_dir.path().join("notes.md"), "# Notes\n").expect("Should write notes.md");
+	crate::test::support::init_logger_once();
+
+	let app = test::init_service(
+		App::new()
+			.app_data(cofg_for(temp_dir.path(), "{}"))
+			.service(main_req),
+	)
+	.await;
+
+	let req = test::TestRequest::get().uri(uri).to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::OK);
+	let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+	assert_eq!(
+		body.contains(r#"<span class="line" id="L1">"#),
+		source,
+		"{body}"
+	);
+	assert_eq!(body.contains("<h1>Notes</h1>"), !source, "{body}");
+}
+
+#[test_case("/docs?x=1", StatusCode::MOVED_PERMANENTLY, Some("/docs/?x=1"), "" ; "redirect keeps the query")]
+#[test_case("/docs/", StatusCode::OK, None, "<h1>Docs</h1>" ; "index is rendered")]
+#[test_case("/docs?view=list", StatusCode::OK, None, "index.md" ; "view skips the index")]
+#[actix_web::test]
+async fn test_directory_index(uri: &str, status: StatusCode, location: Option<&str>, body: &str) {
+	let temp_dir = create_test_dir();
+	std::fs::create_dir(temp_dir.path().join("docs")).expect("Should create docs");
+	std::fs::write(temp_dir.path().join("docs/index.md"), "# Docs\n")
+		.expect("Should write index.md");
+	crate::test::support::init_logger_once();
+
+	let app = test::init_service(
+		App::new()
+			.app_data(cofg_for(temp_dir.path(), "directory:\n  index: [index.md]"))
+			.service(main_req),
+	)
+	.await;
+
+	let req = test::TestRequest::get().uri(uri).to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), status);
+	assert_eq!(
+		resp.headers().get("location").map(|v| v.to_str().unwrap()),
+		location
+	);
+	let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+	assert!(html.contains(body), "{html}");
+}
+
+#[test_case("/app/users/42", StatusCode::OK, "<div id=root>" ; "client route gets the spa file")]
+#[test_case("/app/logo.txt", StatusCode::OK, "logo" ; "existing file is served")]
+#[test_case("/other/x", StatusCode::NOT_FOUND, "404" ; "outside the prefix")]
+#[actix_web::test]
+async fn test_spa_fallback_request(uri: &str, status: StatusCode, body: &str) {
+	let temp_dir = create_test_dir();
+	std::fs::create_dir(temp_dir.path().join("app")).expect("Should create app");
+	std::fs::write(temp_dir.path().join("app/index.html"), "<div id=root>")
+		.expect("Should write index.html");
+	std::fs::write(temp_dir.path().join("app/logo.txt"), "logo").expect("Should write logo.txt");
+	crate::test::support::init_logger_once();
+
+	let app = test::init_service(
+		App::new()
+			.app_data(cofg_for(temp_dir.path(), "spa:\n  /app: app/index.html"))
+			.service(main_req),
+	)
+	.await;
+
+	let req = test::TestRequest::get().uri(uri).to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), status);
+	let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+	assert!(html.contains(body), "{html}");
+}
+
+#[test_case("/public.txt", StatusCode::OK ; "not ignored")]
+#[test_case("/secret/key.txt", StatusCode::NOT_FOUND ; "file in ignored directory")]
+#[test_case("/secret", StatusCode::NOT_FOUND ; "ignored directory")]
+#[test_case("/notes.draft", StatusCode::NOT_FOUND ; "ignored by tocignore")]
+#[test_case("/docs/", StatusCode::NOT_FOUND ; "ignored index file")]
+#[test_case("/app/users/42", StatusCode::NOT_FOUND ; "ignored spa file")]
+#[actix_web::test]
+async fn test_ig_deny_request(uri: &str, status: StatusCode) {
+	let temp_dir = create_test_dir();
+	for dir in ["secret", "docs", "app"] {
+		std::fs::create_dir(temp_dir.path().join(dir)).expect("Should create dir");
+	}
+	for (file, content) in [
+		("public.txt", "public"),
+		("secret/key.txt", "key"),
+		("notes.draft", "draft"),
+		(".tocignore", "*.draft\nindex.md\napp/shell.html"),
+		("docs/index.md", "# Docs"),
+		("app/shell.html", "<div id=root>"),
+	] {
+		std::fs::write(temp_dir.path().join(file), content).expect("Should write file");
+	}
+	crate::test::support::init_logger_once();
+
+	let app = test::init_service(
+		App::new()
+			.app_data(cofg_for(
+				temp_dir.path(),
+				"toc:\n  ig_deny: true\n  ig: [secret]\ndirectory:\n  index: [index.md]\nspa:\n  /app: app/shell.html",
+			))
+			.service(main_req),
+	)
+	.await;
+
+	let req = test::TestRequest::get().uri(uri).to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), status);
+}
+
+#[test_case("/old?a=1", StatusCode::MOVED_PERMANENTLY, Some("/notes.md?a=1"), "" ; "redirect")]
+#[test_case("/latest", StatusCode::OK, None, "<h1>Notes</h1>" ; "rewrite renders the target")]
+#[test_case("/latest-src", StatusCode::OK, None, r#"<span class="line" id="L1">"# ; "rewrite target query")]
+#[test_case("/gone", StatusCode::NOT_FOUND, None, "" ; "rewrite to a missing file")]
+#[actix_web::test]
+async fn test_redirects_request(uri: &str, status: StatusCode, location: Option<&str>, body: &str) {
+	let temp_dir = create_test_dir();
+	std::fs::write(temp_dir.path().join("notes.md"), "# Notes\n").expect("Should write notes.md");
+	crate::test::support::init_logger_once();
+
+	let app = test::init_service(
+		App::new()
+			.app_data(cofg_for(
+				temp_dir.path(),
+				r#"
+redirects:
+  - from: /old
+    to: /notes.md
+  - from: /latest
+    to: /notes.md
+    rewrite: true
+  - from: /latest-src
+    to: /notes.md?view
+    rewrite: true
+  - from: /gone
+    to: /missing.md
+    rewrite: true
+"#,
+			))
+			.service(main_req),
+	)
+	.await;
+
+	let req = test::TestRequest::get().uri(uri).to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), status);
+	assert_eq!(
+		resp.headers().get("location").map(|v| v.to_str().unwrap()),
+		location
+	);
+	let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+	assert!(html.contains(body), "{html}");
+}
+
+#[test_case("/files/a.pdf", "content-disposition", "attachment" ; "direct request")]
+#[test_case("/download", "content-disposition", "attachment" ; "rewrite target")]
+#[test_case("/app/users/42", "cache-control", "no-cache" ; "spa file")]
+#[actix_web::test]
+async fn test_headers_match_served_path(uri: &str, name: &str, value: &str) {
+	let temp_dir = create_test_dir();
+	for dir in ["files", "app"] {
+		std::fs::create_dir(temp_dir.path().join(dir)).expect("Should create dir");
+	}
+	std::fs::write(temp_dir.path().join("files/a.pdf"), "%PDF").expect("Should write a.pdf");
+	std::fs::write(temp_dir.path().join("app/index.html"), "<div id=root>")
+		.expect("Should write index.html");
+	crate::test::support::init_logger_once();
+
+	let app = test::init_service(
+		App::new()
+			.app_data(cofg_for(
+				temp_dir.path(),
+				r#"
+redirects:
+  - from: /download
+    to: /files/a.pdf
+    rewrite: true
+spa:
+  /app: app/index.html
+headers:
+  - ext: [pdf]
+    headers:
+      Content-Disposition: attachment
+  - path: app/index.html
+    headers:
+      Cache-Control: no-cache
+"#,
+			))
+			.service(main_req),
+	)
+	.await;
+
+	let req = test::TestRequest::get().uri(uri).to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::OK);
+	assert_eq!(
+		resp.headers().get(name).map(|v| v.to_str().unwrap()),
+		Some(value)
+	);
+}
@@ -67,3 +67,18 @@ pub(crate) fn assert_status_in(status: StatusCode, allowed: &[StatusCode]) {
 		status
 	);
 }
+
+/// Built-in config with `yaml` layered on top, like a user config file
+///
+/// WHY: `Cofg::new_from_str` needs every section; list-valued sections (`redirects`, `headers`)
+/// are easier to write as YAML than as nested struct literals.
+pub(crate) fn cofg_with(yaml: &str) -> crate::cofg::config::Cofg {
+	use config::{Config, File, FileFormat::Yaml};
+	Config::builder()
+		.add_source(File::from_str(crate::cofg::config::BUILD_COFG, Yaml))
+		.add_source(File::from_str(yaml, Yaml))
+		.build()
+		.unwrap()
+		.try_deserialize()
+		.unwrap()
+}